@@ -0,0 +1,529 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use xmltree::{Element, XMLNode};
+
+/// A file or directory returned by a WebDAV PROPFIND
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// Path relative to the root of the requested dav collection, always starts with '/'
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub display_name: Option<String>,
+    /// Size in bytes, for directories the total size of the contents
+    pub size: Option<i64>,
+    /// Unix timestamp in seconds
+    pub last_modified: Option<i64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub permissions: Option<String>,
+    /// Server wide id of the file, stays the same when the file is moved
+    pub file_id: Option<String>,
+    pub quota_used: Option<i64>,
+    /// Negative values mean the quota is unknown (-1, -2) or unlimited (-3)
+    pub quota_available: Option<i64>,
+    /// Path the item was deleted from, only set for trash bin items
+    pub original_location: Option<String>,
+    /// Unix timestamp in seconds, only set for trash bin items
+    pub deletion_time: Option<i64>,
+}
+
+impl Entry {
+    /// Returns the file name of the entry, the root has an empty name
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Properties requested for files and directories, the oc namespace has to be declared by the request
+pub const FILE_PROPS: &str = "
+        <d:getlastmodified/>
+        <d:getcontentlength/>
+        <d:getcontenttype/>
+        <oc:permissions/>
+        <d:resourcetype/>
+        <d:getetag/>
+        <d:quota-used-bytes/>
+        <d:quota-available-bytes/>
+        <oc:size/>
+        <oc:fileid/>
+        <d:displayname/>
+      ";
+
+/// A collaborative system tag
+#[derive(Debug, Clone, Serialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub user_visible: bool,
+    pub user_assignable: bool,
+}
+
+/// A comment on a file
+#[derive(Debug, Clone, Serialize)]
+pub struct Comment {
+    pub id: String,
+    pub message: String,
+    pub actor_id: String,
+    pub actor_display_name: Option<String>,
+    /// Unix timestamp in seconds
+    pub created: Option<i64>,
+}
+
+/// Filters of a DASL SEARCH, every supplied filter has to match
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    /// Pattern the name is like, % matches any characters and _ a single character
+    pub name: Option<String>,
+    /// Pattern the mime type is like, Ex: image/%
+    pub mime: Option<String>,
+    /// Size in bytes
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    /// Unix timestamp in seconds, after includes the time and before excludes it
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+impl SearchQuery {
+    /// Builds the SEARCH request body, `scope` is the href of the directory to search in, Ex: /files/user/Documents
+    pub fn to_xml(&self, scope: &str) -> String {
+        let mut conditions = vec![];
+        if let Some(name) = &self.name {
+            conditions.push(condition("like", "d:displayname", name));
+        }
+        if let Some(mime) = &self.mime {
+            conditions.push(condition("like", "d:getcontenttype", mime));
+        }
+        if let Some(size) = self.min_size {
+            conditions.push(condition("gte", "oc:size", &size.to_string()));
+        }
+        if let Some(size) = self.max_size {
+            conditions.push(condition("lte", "oc:size", &size.to_string()));
+        }
+        if let Some(time) = self.modified_after {
+            let time = time.to_string();
+            conditions.push(condition("gte", "d:getlastmodified", &time));
+        }
+        if let Some(time) = self.modified_before {
+            let time = time.to_string();
+            conditions.push(condition("lt", "d:getlastmodified", &time));
+        }
+
+        let filter = match conditions.len() {
+            0 => condition("like", "d:displayname", "%"),
+            1 => conditions.remove(0),
+            _ => format!("<d:and>{}</d:and>", conditions.join("")),
+        };
+        let limit = self
+            .limit
+            .map(|l| {
+                format!("<d:limit><d:nresults>{}</d:nresults></d:limit>", l)
+            })
+            .unwrap_or_default();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<d:searchrequest xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+  <d:basicsearch>
+    <d:select>
+      <d:prop>{props}</d:prop>
+    </d:select>
+    <d:from>
+      <d:scope>
+        <d:href>{scope}</d:href>
+        <d:depth>infinity</d:depth>
+      </d:scope>
+    </d:from>
+    <d:where>{filter}</d:where>
+    <d:orderby/>
+    {limit}
+  </d:basicsearch>
+</d:searchrequest>",
+            props = FILE_PROPS,
+            scope = escape(scope),
+            filter = filter,
+            limit = limit
+        )
+    }
+}
+
+fn condition(operator: &str, prop: &str, literal: &str) -> String {
+    format!(
+        "<d:{op}><d:prop><{prop}/></d:prop><d:literal>{literal}</d:literal></d:{op}>",
+        op = operator,
+        prop = prop,
+        literal = escape(literal)
+    )
+}
+
+/// Escapes the characters that are not allowed in xml text
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Parses a multistatus PROPFIND response, `root` is the url path of the dav collection and is stripped from each href
+pub fn parse_multistatus(xml: &str, root: &str) -> Result<Vec<Entry>> {
+    parse_responses(xml, root, |path, prop| {
+        let text = |name: &str| prop_text(prop, name);
+        let number = |name: &str| -> Option<i64> {
+            text(name).and_then(|t| t.trim().parse().ok())
+        };
+
+        let is_dir = prop
+            .and_then(|p| p.get_child("resourcetype"))
+            .is_some_and(|r| r.get_child("collection").is_some());
+
+        Entry {
+            path,
+            is_dir,
+            display_name: text("displayname")
+                .or_else(|| text("trashbin-filename")),
+            size: number("getcontentlength").or_else(|| number("size")),
+            last_modified: text("getlastmodified")
+                .and_then(|t| DateTime::parse_from_rfc2822(&t).ok())
+                .map(|t| t.timestamp()),
+            content_type: text("getcontenttype"),
+            etag: text("getetag").map(|t| t.trim_matches('"').to_string()),
+            permissions: text("permissions"),
+            file_id: text("fileid"),
+            quota_used: number("quota-used-bytes"),
+            quota_available: number("quota-available-bytes"),
+            original_location: text("trashbin-original-location"),
+            deletion_time: number("trashbin-deletion-time"),
+        }
+    })
+}
+
+/// Parses a multistatus response of the systemtags endpoints, the collection itself is skipped
+pub fn parse_tags(xml: &str) -> Result<Vec<Tag>> {
+    let tags = parse_responses(xml, "", |_, prop| {
+        let flag =
+            |name: &str| prop_text(prop, name).as_deref() == Some("true");
+        Some(Tag {
+            id: prop_text(prop, "id")?,
+            name: prop_text(prop, "display-name")?,
+            user_visible: flag("user-visible"),
+            user_assignable: flag("user-assignable"),
+        })
+    })?;
+    Ok(tags.into_iter().flatten().collect())
+}
+
+/// Parses a multistatus response of the comments endpoint
+pub fn parse_comments(xml: &str) -> Result<Vec<Comment>> {
+    let comments = parse_responses(xml, "", |_, prop| {
+        Some(Comment {
+            id: prop_text(prop, "id")?,
+            message: prop_text(prop, "message").unwrap_or_default(),
+            actor_id: prop_text(prop, "actorId")?,
+            actor_display_name: prop_text(prop, "actorDisplayName"),
+            created: prop_text(prop, "creationDateTime")
+                .and_then(|t| DateTime::parse_from_rfc2822(&t).ok())
+                .map(|t| t.timestamp()),
+        })
+    })?;
+    Ok(comments.into_iter().flatten().collect())
+}
+
+/// Checks a multistatus PROPPATCH response, returns an Error if the property `name` was not updated
+pub fn check_proppatch(xml: &str, name: &str) -> Result<()> {
+    let multistatus = Element::parse(xml.as_bytes())?;
+    let status = elements(&multistatus, "response")
+        .flat_map(|r| elements(r, "propstat"))
+        .find(|p| p.get_child("prop").and_then(|p| p.get_child(name)).is_some())
+        .and_then(|p| p.get_child("status"))
+        .and_then(|s| s.get_text())
+        .ok_or_else(|| {
+            anyhow!("Server did not return the status of {}", name)
+        })?;
+    if status.contains(" 200 ") {
+        Ok(())
+    } else {
+        Err(anyhow!("Unable to set {}, {}", name, status))
+    }
+}
+
+/// Calls `f` with the path and found properties of every response in a multistatus
+fn parse_responses<T>(
+    xml: &str,
+    root: &str,
+    f: impl Fn(PathBuf, Option<&Element>) -> T,
+) -> Result<Vec<T>> {
+    let multistatus = Element::parse(xml.as_bytes())?;
+    let root = root.trim_end_matches('/');
+
+    let mut results = vec![];
+    for response in elements(&multistatus, "response") {
+        let href = response
+            .get_child("href")
+            .and_then(|e| e.get_text())
+            .ok_or_else(|| anyhow!("Response is missing href"))?;
+        let href = percent_decode_str(&href).decode_utf8_lossy().to_string();
+        let relative = href.strip_prefix(root).unwrap_or(&href);
+        let path = PathBuf::from("/").join(relative.trim_matches('/'));
+
+        // only use properties the server could find, missing ones are in a 404 propstat
+        let prop = elements(response, "propstat")
+            .find(|p| {
+                p.get_child("status")
+                    .and_then(|s| s.get_text())
+                    .is_some_and(|s| s.contains(" 200 "))
+            })
+            .and_then(|p| p.get_child("prop"));
+
+        results.push(f(path, prop));
+    }
+
+    Ok(results)
+}
+
+fn prop_text(prop: Option<&Element>, name: &str) -> Option<String> {
+    prop.and_then(|p| p.get_child(name))
+        .and_then(|e| e.get_text())
+        .map(|t| t.to_string())
+}
+
+/// Iterates over the child elements with the local name
+fn elements<'a>(
+    parent: &'a Element,
+    name: &'a str,
+) -> impl Iterator<Item = &'a Element> + 'a {
+    parent.children.iter().filter_map(move |node| match node {
+        XMLNode::Element(e) if e.name == name => Some(e),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LIST: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/files/user/</d:href>
+  <d:propstat>
+   <d:prop>
+    <d:getlastmodified>Tue, 02 Feb 2021 10:00:00 GMT</d:getlastmodified>
+    <d:resourcetype><d:collection/></d:resourcetype>
+    <oc:size>3072</oc:size>
+    <d:quota-used-bytes>2048</d:quota-used-bytes>
+    <d:quota-available-bytes>-3</d:quota-available-bytes>
+   </d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+  <d:propstat>
+   <d:prop><d:getcontentlength/></d:prop>
+   <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/files/user/My%20Notes.txt</d:href>
+  <d:propstat>
+   <d:prop>
+    <d:getcontentlength>1024</d:getcontentlength>
+    <d:getcontenttype>text/plain</d:getcontenttype>
+    <d:displayname>My Notes.txt</d:displayname>
+    <d:getetag>&quot;abc123&quot;</d:getetag>
+    <oc:permissions>RGDNVW</oc:permissions>
+    <oc:fileid>1234</oc:fileid>
+    <d:resourcetype/>
+   </d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn parse_list() {
+        let entries =
+            parse_multistatus(LIST, "/remote.php/dav/files/user/").unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let root = &entries[0];
+        assert_eq!(root.path, PathBuf::from("/"));
+        assert!(root.is_dir);
+        assert_eq!(root.size, Some(3072));
+        assert_eq!(root.last_modified, Some(1612260000));
+        assert_eq!(root.quota_used, Some(2048));
+        assert_eq!(root.quota_available, Some(-3));
+
+        let file = &entries[1];
+        assert_eq!(file.path, PathBuf::from("/My Notes.txt"));
+        assert_eq!(file.name(), "My Notes.txt");
+        assert!(!file.is_dir);
+        assert_eq!(file.size, Some(1024));
+        assert_eq!(file.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(file.display_name.as_deref(), Some("My Notes.txt"));
+        assert_eq!(file.etag.as_deref(), Some("abc123"));
+        assert_eq!(file.permissions.as_deref(), Some("RGDNVW"));
+        assert_eq!(file.file_id.as_deref(), Some("1234"));
+    }
+
+    #[test]
+    fn proppatch_status() {
+        let response = |status: &str| {
+            format!(
+                r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/files/user/Documents/</d:href>
+  <d:propstat>
+   <d:prop><oc:favorite/></d:prop>
+   <d:status>HTTP/1.1 {}</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#,
+                status
+            )
+        };
+        check_proppatch(&response("200 OK"), "favorite").unwrap();
+        check_proppatch(&response("403 Forbidden"), "favorite")
+            .expect_err("Property was not updated");
+        check_proppatch(&response("200 OK"), "tags")
+            .expect_err("Property is missing");
+    }
+
+    #[test]
+    fn parse_trash() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:nc="http://nextcloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/trashbin/user/trash/report.pdf.d1612345678</d:href>
+  <d:propstat>
+   <d:prop>
+    <nc:trashbin-filename>report.pdf</nc:trashbin-filename>
+    <nc:trashbin-original-location>Documents/report.pdf</nc:trashbin-original-location>
+    <nc:trashbin-deletion-time>1612345678</nc:trashbin-deletion-time>
+    <d:getcontentlength>512</d:getcontentlength>
+    <d:resourcetype/>
+   </d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#;
+        let entries =
+            parse_multistatus(xml, "/remote.php/dav/trashbin/user/trash")
+                .unwrap();
+        let item = &entries[0];
+        assert_eq!(item.name(), "report.pdf.d1612345678");
+        assert_eq!(item.display_name.as_deref(), Some("report.pdf"));
+        assert_eq!(
+            item.original_location.as_deref(),
+            Some("Documents/report.pdf")
+        );
+        assert_eq!(item.deletion_time, Some(1612345678));
+        assert_eq!(item.size, Some(512));
+    }
+
+    #[test]
+    fn parse_tag_list() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/systemtags/</d:href>
+  <d:propstat>
+   <d:prop><oc:id/><oc:display-name/></d:prop>
+   <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/systemtags/3</d:href>
+  <d:propstat>
+   <d:prop>
+    <oc:id>3</oc:id>
+    <oc:display-name>deliverable</oc:display-name>
+    <oc:user-visible>true</oc:user-visible>
+    <oc:user-assignable>false</oc:user-assignable>
+   </d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#;
+        let tags = parse_tags(xml).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].id, "3");
+        assert_eq!(tags[0].name, "deliverable");
+        assert!(tags[0].user_visible);
+        assert!(!tags[0].user_assignable);
+    }
+
+    #[test]
+    fn parse_comment_list() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/comments/files/1234/7</d:href>
+  <d:propstat>
+   <d:prop>
+    <oc:id>7</oc:id>
+    <oc:message>build 1234 passed QA</oc:message>
+    <oc:actorId>ci</oc:actorId>
+    <oc:actorDisplayName>CI Bot</oc:actorDisplayName>
+    <oc:creationDateTime>Tue, 02 Feb 2021 10:00:00 GMT</oc:creationDateTime>
+   </d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#;
+        let comments = parse_comments(xml).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, "7");
+        assert_eq!(comments[0].message, "build 1234 passed QA");
+        assert_eq!(comments[0].actor_display_name.as_deref(), Some("CI Bot"));
+        assert_eq!(comments[0].created, Some(1612260000));
+    }
+
+    #[test]
+    fn search_xml() {
+        let query = SearchQuery {
+            name: Some(String::from("%a&b%")),
+            min_size: Some(1024),
+            ..SearchQuery::default()
+        };
+        let xml = query.to_xml("/files/user/Documents");
+        assert!(xml.contains("<d:href>/files/user/Documents</d:href>"));
+        assert!(xml.contains("<d:and>"));
+        assert!(xml.contains("<d:literal>%a&amp;b%</d:literal>"));
+        assert!(xml.contains("<d:gte><d:prop><oc:size/></d:prop>"));
+        assert!(!xml.contains("<d:limit>"));
+        Element::parse(xml.as_bytes()).expect("Should be valid xml");
+
+        let query = SearchQuery { limit: Some(5), ..SearchQuery::default() };
+        let xml = query.to_xml("/files/user");
+        assert!(!xml.contains("<d:and>"));
+        assert!(xml.contains("<d:nresults>5</d:nresults>"));
+    }
+
+    #[test]
+    fn search_xml_modified() {
+        let query = SearchQuery {
+            modified_after: Some(1612224000),
+            modified_before: Some(1612310400),
+            ..SearchQuery::default()
+        };
+        let xml = query.to_xml("/files/user");
+        assert!(xml.contains(
+            "<d:gte><d:prop><d:getlastmodified/></d:prop><d:literal>1612224000</d:literal></d:gte>"
+        ));
+        assert!(xml.contains(
+            "<d:lt><d:prop><d:getlastmodified/></d:prop><d:literal>1612310400</d:literal></d:lt>"
+        ));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        parse_multistatus("not xml", "/").expect_err("Should fail to parse");
+    }
+}
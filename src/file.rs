@@ -1,58 +1,166 @@
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::{fs, fs::File};
+use std::{fs, fs::File, fs::OpenOptions};
 
+use age::secrecy::SecretString;
 use anyhow::{anyhow, Result};
-use base64::{decode, encode};
 use bytes::Bytes;
-use dirs::home_dir;
+use dirs::{config_dir, home_dir};
 use lazy_static::lazy_static;
+use log::info;
+use serde::{Deserialize, Serialize};
+use url::Url;
 
+use super::ocs::Capabilities;
+use super::util;
 use super::Credentials;
 
+/// Header every age encrypted file starts with
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
 lazy_static! {
     pub static ref HISTORY_PATH: PathBuf =
         home_dir().unwrap().join(".cache/nxcloud_history.txt");
-    pub static ref CREDS_PATH: PathBuf =
+    pub static ref CONFIG_PATH: PathBuf = config_dir().unwrap().join("nxcloud");
+    pub static ref CREDS_PATH: PathBuf = CONFIG_PATH.join("credentials");
+    static ref CAPABILITIES_PATH: PathBuf =
+        CONFIG_PATH.join("capabilities.json");
+    static ref LEGACY_CREDS_PATH: PathBuf =
         home_dir().unwrap().join(".cache/nxcloud_auth.txt");
 }
 
 impl Credentials {
     pub fn file_read_default() -> Result<Self> {
+        if !CREDS_PATH.exists() && LEGACY_CREDS_PATH.exists() {
+            info!("Migrating credentials out of the cache directory");
+            let creds = Self::file_read(LEGACY_CREDS_PATH.as_ref())?;
+            creds.file_write_default()?;
+            file_delete(LEGACY_CREDS_PATH.as_ref())?;
+            return Ok(creds);
+        }
         Self::file_read(CREDS_PATH.as_ref())
     }
 
     fn file_read(path: &Path) -> Result<Self> {
-        let contents = fs::read_to_string(path)?;
-        let contents_decoded = decode(contents)?;
-        let decoded = String::from_utf8_lossy(&contents_decoded);
-        let v: Vec<&str> = decoded.split(' ').collect();
-
-        if v.len() != 3 {
-            return Err(anyhow!("Unexpect format"));
+        let contents = fs::read(path)?;
+        if contents.starts_with(AGE_HEADER) {
+            let passphrase = util::get_passphrase("Credentials passphrase: ")?;
+            let decrypted = String::from_utf8(decrypt(&contents, passphrase)?)?;
+            return Ok(Self::deserialize(&decrypted)?.0);
         }
 
-        Ok(Self::from(v[0], v[1], v[2])?)
+        let (creds, legacy) = Self::deserialize(&String::from_utf8(contents)?)?;
+        if legacy {
+            info!("Migrating credentials file to the new format");
+            creds.file_write(path, None)?;
+        }
+        Ok(creds)
     }
 
     pub fn file_write_default(&self) -> Result<()> {
-        Self::file_write(&self, CREDS_PATH.as_ref())
+        self.file_write(CREDS_PATH.as_ref(), None)
     }
 
-    fn file_write(&self, path: &Path) -> Result<()> {
+    /// Writes the credentials to the config directory encrypted with a passphrase
+    pub fn file_write_encrypted_default(
+        &self,
+        passphrase: SecretString,
+    ) -> Result<()> {
+        self.file_write(CREDS_PATH.as_ref(), Some(passphrase))
+    }
+
+    fn file_write(
+        &self,
+        path: &Path,
+        passphrase: Option<SecretString>,
+    ) -> Result<()> {
         file_delete(path)?;
 
-        let encoded = encode(self.display());
-        let mut file = File::create(&path)?;
-        file.write_all(encoded.as_bytes())?;
-        Ok(())
+        let serialized = self.serialize()?;
+        let contents = match passphrase {
+            Some(passphrase) => encrypt(serialized.as_bytes(), passphrase)?,
+            None => serialized.into_bytes(),
+        };
+        create_private_file(path, &contents)
     }
 
     pub fn file_delete_default() -> Result<()> {
+        file_delete(LEGACY_CREDS_PATH.as_ref())?;
         file_delete(CREDS_PATH.as_ref())
     }
 }
 
+/// Cached capabilities with the server they belong to
+#[derive(Serialize, Deserialize)]
+struct CapabilitiesRecord {
+    server: String,
+    capabilities: Capabilities,
+}
+
+impl Capabilities {
+    /// Reads the capabilities cached for the server, fails if they were cached for another server
+    pub fn file_read_default(server: &Url) -> Result<Self> {
+        let record: CapabilitiesRecord =
+            serde_json::from_slice(&fs::read(CAPABILITIES_PATH.as_path())?)?;
+        if record.server != server.as_str() {
+            return Err(anyhow!("Capabilities are cached for another server"));
+        }
+        Ok(record.capabilities)
+    }
+
+    pub fn file_write_default(&self, server: &Url) -> Result<()> {
+        let record = CapabilitiesRecord {
+            server: server.to_string(),
+            capabilities: self.clone(),
+        };
+        create_private_file(
+            CAPABILITIES_PATH.as_ref(),
+            &serde_json::to_vec(&record)?,
+        )
+    }
+
+    pub fn file_delete_default() -> Result<()> {
+        file_delete(CAPABILITIES_PATH.as_ref())
+    }
+}
+
+/// Encrypts data with an scrypt derived key from the passphrase
+fn encrypt(data: &[u8], passphrase: SecretString) -> Result<Vec<u8>> {
+    let recipient = age::scrypt::Recipient::new(passphrase);
+    Ok(age::encrypt(&recipient, data)?)
+}
+
+/// Decrypts data that was encrypted with `encrypt`
+fn decrypt(data: &[u8], passphrase: SecretString) -> Result<Vec<u8>> {
+    let identity = age::scrypt::Identity::new(passphrase);
+    age::decrypt(&identity, data).map_err(|_| {
+        anyhow!("Unable to decrypt credentials, wrong passphrase?")
+    })
+}
+
+/// Creates a file only readable and writable by the current user, creating parent directories as needed
+fn create_private_file(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+        if let Some(parent) = path.parent() {
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(parent)?;
+        }
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    Ok(())
+}
+
 pub fn file_delete(path: &Path) -> Result<()> {
     if path.exists() && path.is_file() {
         fs::remove_file(path)?;
@@ -62,14 +170,14 @@ pub fn file_delete(path: &Path) -> Result<()> {
 
 pub fn create_file(path: &Path, data: &Bytes) -> Result<()> {
     if !path.exists() && !path.is_dir() {
-        let mut file = File::create(&path)?;
+        let mut file = File::create(path)?;
         file.write_all(data)?;
     }
     Ok(())
 }
 
 pub fn read_file(path: &Path) -> Result<Bytes> {
-    let contents = fs::read_to_string(path)?;
+    let contents = fs::read(path)?;
     Ok(Bytes::from(contents))
 }
 
@@ -77,7 +185,6 @@ pub fn read_file(path: &Path) -> Result<Bytes> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use url::Url;
 
     #[test]
     fn read_user_no_file() {
@@ -92,7 +199,7 @@ mod tests {
         let creds =
             Credentials::from("user", "pass", "https://cloud.example.com")
                 .unwrap();
-        creds.file_write(path).expect("File should be created");
+        creds.file_write(path, None).expect("File should be created");
         file_delete(path).unwrap();
     }
 
@@ -102,12 +209,12 @@ mod tests {
         let creds =
             Credentials::from("user", "pass", "https://cloud.example.com")
                 .unwrap();
-        creds.file_write(path).expect("File should be created");
+        creds.file_write(path, None).expect("File should be created");
         let creds2 =
             Credentials::from("user2", "pass2", "cloud.example2.com").unwrap();
 
         // https should be added dynamically
-        creds2.file_write(path).expect("File should be created");
+        creds2.file_write(path, None).expect("File should be created");
         let resp = Credentials::file_read(path).unwrap();
         assert_eq!(resp.username, String::from("user2"));
         assert_eq!(resp.password, String::from("pass2"));
@@ -124,7 +231,7 @@ mod tests {
         let creds =
             Credentials::from("user", "pass", "https://cloud.example.com")
                 .unwrap();
-        creds.file_write(path).expect("File should be created");
+        creds.file_write(path, None).expect("File should be created");
         let resp = Credentials::file_read(path).unwrap();
         assert_eq!(resp.username, String::from("user"));
         assert_eq!(resp.password, String::from("pass"));
@@ -141,4 +248,91 @@ mod tests {
         );
         file_delete(path).unwrap();
     }
+
+    #[test]
+    fn write_and_read_special_characters() {
+        let path = Path::new("test_write_and_read_special_characters.txt");
+        let creds = Credentials::from(
+            "first last",
+            "p@ss word \"quoted\"",
+            "https://cloud.example.com",
+        )
+        .unwrap();
+        creds.file_write(path, None).expect("File should be created");
+        let resp = Credentials::file_read(path).unwrap();
+        assert_eq!(resp.username, String::from("first last"));
+        assert_eq!(resp.password, String::from("p@ss word \"quoted\""));
+        file_delete(path).unwrap();
+    }
+
+    #[test]
+    fn read_legacy_format_migrates() {
+        let path = Path::new("test_read_legacy_format_migrates.txt");
+        let legacy = base64::encode("user pass https://cloud.example.com/");
+        fs::write(path, legacy).unwrap();
+
+        let resp = Credentials::file_read(path).unwrap();
+        assert_eq!(resp.username, String::from("user"));
+        assert_eq!(resp.password, String::from("pass"));
+        assert!(fs::read_to_string(path).unwrap().starts_with('{'));
+
+        let resp = Credentials::file_read(path).unwrap();
+        assert_eq!(resp.username, String::from("user"));
+        file_delete(path).unwrap();
+    }
+
+    #[test]
+    fn rewrite_keeps_created() {
+        let path = Path::new("test_rewrite_keeps_created.txt");
+        let record = "{\"version\":1,\"profile\":{\"name\":\"default\",\"created\":1612345678},\
+            \"username\":\"user\",\"password\":\"pass\",\"server\":\"https://cloud.example.com/\"}";
+        let (creds, _) = Credentials::deserialize(record).unwrap();
+        creds.file_write(path, None).unwrap();
+        assert!(fs::read_to_string(path)
+            .unwrap()
+            .contains("\"created\":1612345678"));
+        file_delete(path).unwrap();
+    }
+
+    #[test]
+    fn read_malformed_format() {
+        let path = Path::new("test_read_malformed_format.txt");
+        fs::write(path, base64::encode("user pass")).unwrap();
+        Credentials::file_read(path).expect_err("Missing server should fail");
+
+        fs::write(path, "{\"version\": 1}").unwrap();
+        Credentials::file_read(path).expect_err("Missing fields should fail");
+        file_delete(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn write_user_private_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = Path::new("test_write_user_private_permissions.txt");
+        let creds =
+            Credentials::from("user", "pass", "https://cloud.example.com")
+                .unwrap();
+        creds.file_write(path, None).expect("File should be created");
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        file_delete(path).unwrap();
+    }
+
+    #[test]
+    fn encrypt_and_decrypt() {
+        let data = b"user pass https://cloud.example.com/";
+        let encrypted =
+            encrypt(data, SecretString::from("secret".to_owned())).unwrap();
+        assert!(encrypted.starts_with(AGE_HEADER));
+        assert_ne!(&encrypted[..], &data[..]);
+
+        decrypt(&encrypted, SecretString::from("wrong".to_owned()))
+            .expect_err("Wrong passphrase should fail");
+        let decrypted =
+            decrypt(&encrypted, SecretString::from("secret".to_owned()))
+                .unwrap();
+        assert_eq!(&decrypted[..], &data[..]);
+    }
 }
@@ -1,15 +1,55 @@
+use std::fmt;
 use std::path::Path;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use bytes::Bytes;
-use reqwest::{Client, ClientBuilder, Method};
+use reqwest::{
+    Client, ClientBuilder, Method, RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use url::Url;
 
+use super::dav::{self, Comment, Entry, SearchQuery, Tag};
+use super::ocs::{
+    self, Activity, AppList, AppPassword, Capabilities, GroupList,
+    Notification, RemoteShare, ServerStatus, Share, User, UserList, UserStatus,
+};
+use super::util;
 use super::Credentials;
 
+const USERS_EXT: &str = "ocs/v1.php/cloud/users";
+const APPS_EXT: &str = "ocs/v1.php/cloud/apps";
+const GROUPS_EXT: &str = "ocs/v1.php/cloud/groups";
+const SHARES_EXT: &str = "ocs/v2.php/apps/files_sharing/api/v1/shares";
+const ACTIVITY_EXT: &str = "ocs/v2.php/apps/activity/api/v2/activity";
+const NOTIFICATIONS_EXT: &str =
+    "ocs/v2.php/apps/notifications/api/v2/notifications";
+const USER_STATUS_EXT: &str = "ocs/v2.php/apps/user_status/api/v1/user_status";
+const REMOTE_SHARES_EXT: &str =
+    "ocs/v2.php/apps/files_sharing/api/v1/remote_shares";
+
+/// Header NextCloud sets on responses while it is in maintenance mode
+const MAINTENANCE_HEADER: &str = "X-Nextcloud-Maintenance-Mode";
+
+/// Error returned for any request while the server is in maintenance mode
+#[derive(Debug)]
+pub struct MaintenanceMode;
+
+impl fmt::Display for MaintenanceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Server is in maintenance mode, try again later")
+    }
+}
+
+impl std::error::Error for MaintenanceMode {}
+
 pub struct Http {
     credentials: Credentials,
     client: Client,
+    /// Path of the files dav collection relative to the server url
+    files_ext: String,
 }
 
 impl Credentials {
@@ -20,169 +60,968 @@ impl Credentials {
 
 impl Http {
     pub fn from(credentials: Credentials) -> Self {
+        let files_ext =
+            format!("remote.php/dav/files/{}", credentials.username);
         Self {
             credentials,
             client: ClientBuilder::new()
                 .timeout(Duration::new(10, 0))
                 .build()
                 .unwrap(),
+            files_ext,
         }
     }
 
-    #[tokio::main]
-    pub async fn get_user(&self) -> Result<String> {
-        let request: String = format!(
-            "{url}{ext}{user}",
+    /// Creates a client for the files of a public share link, Ex: https://cloud.example.com/s/TOKEN
+    /// The share token is used as username, and the share password if it is protected
+    pub fn from_public_link(
+        link: &Url,
+        password: Option<String>,
+    ) -> Result<Self> {
+        let (server, token) = util::parse_public_link(link)?;
+        let credentials =
+            Credentials::new(token, password.unwrap_or_default(), server);
+        let mut http = Self::from(credentials);
+        http.files_ext = String::from("public.php/webdav");
+        Ok(http)
+    }
+
+    pub fn server(&self) -> &Url {
+        &self.credentials.server
+    }
+
+    /// Url of a file or directory in the files dav collection
+    fn files_url(&self, path: &Path) -> String {
+        format!(
+            "{url}{ext}/{path}",
             url = self.credentials.server,
-            ext = "ocs/v1.php/cloud/users/",
-            user = self.credentials.username
-        );
+            ext = self.files_ext,
+            path = path.to_string_lossy().trim_start_matches('/')
+        )
+    }
 
-        let response = self
-            .client
-            .get(&request)
+    /// Builds an OCS request for the endpoint, asking for a json response
+    fn ocs_request(&self, method: Method, ext: &str) -> RequestBuilder {
+        let request: String =
+            format!("{url}{ext}", url = self.credentials.server, ext = ext);
+        self.ocs_request_url(method, &request)
+    }
+
+    /// Builds an OCS request for the endpoint with `ids` appended as percent-encoded path segments
+    fn ocs_request_ids(
+        &self,
+        method: Method,
+        ext: &str,
+        ids: &[&str],
+    ) -> RequestBuilder {
+        self.ocs_request_url(method, self.server_url(ext, ids).as_str())
+    }
+
+    fn ocs_request_url(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client
+            .request(method, url)
             .basic_auth(
                 &self.credentials.username,
                 Some(&self.credentials.password),
             )
             .header("OCS-APIRequest", "true")
-            .send()
-            .await?
-            .error_for_status();
+            .query(&[("format", "json")])
+    }
+
+    /// Sends an OCS request and parses the data of the response
+    async fn ocs_send<T: DeserializeOwned>(
+        request: RequestBuilder,
+    ) -> Result<T> {
+        let response = Self::send(request).await?;
+        ocs::parse(&response.text().await?)
+    }
 
-        Ok(response?.text().await?)
+    /// Sends a request, returns an Error if the response status is not successful
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = Self::check_available(request.send().await?)?;
+        Ok(response.error_for_status()?)
+    }
+
+    /// Returns an Error if the response means no request can succeed, like revoked credentials or maintenance
+    fn check_available(response: Response) -> Result<Response> {
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(anyhow!(
+                "Authentication failed, the app password may have been revoked, please login again"
+            )),
+            StatusCode::SERVICE_UNAVAILABLE
+                if response.headers().contains_key(MAINTENANCE_HEADER) =>
+            {
+                Err(MaintenanceMode.into())
+            }
+            _ => Ok(response),
+        }
     }
 
     #[tokio::main]
-    pub async fn get_file(&self, path: &Path) -> Result<Bytes> {
-        let request: String = format!(
-            "{url}{ext}{user}/{path}",
-            url = self.credentials.server,
-            ext = "remote.php/dav/files/",
-            user = self.credentials.username,
-            path = path.to_string_lossy()
+    pub async fn get_user(&self) -> Result<User> {
+        let ids = [self.credentials.username.as_str()];
+        let request = self.ocs_request_ids(Method::GET, USERS_EXT, &ids);
+        Self::ocs_send(request).await
+    }
+
+    /// Checks the credentials by fetching the user, only the OCS status of the response is used
+    #[tokio::main]
+    pub async fn verify_login(&self) -> Result<()> {
+        let ids = [self.credentials.username.as_str()];
+        let request = self.ocs_request_ids(Method::GET, USERS_EXT, &ids);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Fetches any user, needs admin rights for other users than yourself
+    #[tokio::main]
+    pub async fn get_user_by_id(&self, id: &str) -> Result<User> {
+        let request = self.ocs_request_ids(Method::GET, USERS_EXT, &[id]);
+        Self::ocs_send(request).await
+    }
+
+    /// Lists the ids of users matching `search`, or all users
+    #[tokio::main]
+    pub async fn get_users(
+        &self,
+        search: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>> {
+        let mut query =
+            vec![("search", search.unwrap_or_default().to_string())];
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let request = self.ocs_request(Method::GET, USERS_EXT).query(&query);
+        Ok(Self::ocs_send::<UserList>(request).await?.users)
+    }
+
+    /// Creates a user, `params` are the form parameters of the provisioning API
+    #[tokio::main]
+    pub async fn create_user(&self, params: &[(&str, String)]) -> Result<()> {
+        let request = self.ocs_request(Method::POST, USERS_EXT).form(params);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    pub async fn set_user_enabled(
+        &self,
+        id: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        let action = if enabled { "enable" } else { "disable" };
+        let request =
+            self.ocs_request_ids(Method::PUT, USERS_EXT, &[id, action]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    pub async fn delete_user(&self, id: &str) -> Result<()> {
+        let request = self.ocs_request_ids(Method::DELETE, USERS_EXT, &[id]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Updates a single attribute of a user Ex: quota, email, displayname
+    #[tokio::main]
+    pub async fn edit_user(
+        &self,
+        id: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<()> {
+        let request = self
+            .ocs_request_ids(Method::PUT, USERS_EXT, &[id])
+            .form(&[("key", key), ("value", value)]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Adds a user to a group, or removes the user from it if `add` is false
+    #[tokio::main]
+    pub async fn set_user_group(
+        &self,
+        id: &str,
+        group: &str,
+        add: bool,
+    ) -> Result<()> {
+        let method = if add { Method::POST } else { Method::DELETE };
+        let request = self
+            .ocs_request_ids(method, USERS_EXT, &[id, "groups"])
+            .form(&[("groupid", group)]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    pub async fn resend_welcome(&self, id: &str) -> Result<()> {
+        let request =
+            self.ocs_request_ids(Method::POST, USERS_EXT, &[id, "welcome"]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Lists the ids of groups matching `search`, or all groups
+    #[tokio::main]
+    pub async fn get_groups(
+        &self,
+        search: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>> {
+        let mut query =
+            vec![("search", search.unwrap_or_default().to_string())];
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        let request = self.ocs_request(Method::GET, GROUPS_EXT).query(&query);
+        Ok(Self::ocs_send::<GroupList>(request).await?.groups)
+    }
+
+    #[tokio::main]
+    pub async fn create_group(&self, id: &str) -> Result<()> {
+        let request =
+            self.ocs_request(Method::POST, GROUPS_EXT).form(&[("groupid", id)]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    pub async fn delete_group(&self, id: &str) -> Result<()> {
+        let request = self.ocs_request_ids(Method::DELETE, GROUPS_EXT, &[id]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Lists the ids of the users in a group
+    #[tokio::main]
+    pub async fn get_group_members(&self, id: &str) -> Result<Vec<String>> {
+        let request =
+            self.ocs_request_ids(Method::GET, GROUPS_EXT, &[id, "users"]);
+        Ok(Self::ocs_send::<UserList>(request).await?.users)
+    }
+
+    /// Lists the ids of the users that can administrate a group
+    #[tokio::main]
+    pub async fn get_group_subadmins(&self, id: &str) -> Result<Vec<String>> {
+        let request =
+            self.ocs_request_ids(Method::GET, GROUPS_EXT, &[id, "subadmins"]);
+        Self::ocs_send(request).await
+    }
+
+    /// Lists the ids of installed apps, only enabled or disabled ones if `enabled` is supplied
+    #[tokio::main]
+    pub async fn get_apps(&self, enabled: Option<bool>) -> Result<Vec<String>> {
+        let mut request = self.ocs_request(Method::GET, APPS_EXT);
+        if let Some(enabled) = enabled {
+            let filter = if enabled { "enabled" } else { "disabled" };
+            request = request.query(&[("filter", filter)]);
+        }
+        Ok(Self::ocs_send::<AppList>(request).await?.apps)
+    }
+
+    /// Enables an app, or disables it if `enabled` is false
+    #[tokio::main]
+    pub async fn set_app_enabled(&self, id: &str, enabled: bool) -> Result<()> {
+        let method = if enabled { Method::POST } else { Method::DELETE };
+        let request = self.ocs_request_ids(method, APPS_EXT, &[id]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Creates an app password, the credentials must contain the account password
+    #[tokio::main]
+    pub async fn create_app_password(&self) -> Result<String> {
+        let request =
+            self.ocs_request(Method::GET, "ocs/v2.php/core/getapppassword");
+        Ok(Self::ocs_send::<AppPassword>(request).await?.app_password)
+    }
+
+    /// Revokes the app password of the credentials on the server
+    #[tokio::main]
+    pub async fn revoke_app_password(&self) -> Result<()> {
+        let request =
+            self.ocs_request(Method::DELETE, "ocs/v2.php/core/apppassword");
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    pub async fn get_user_status(&self) -> Result<UserStatus> {
+        Self::ocs_send(self.ocs_request(Method::GET, USER_STATUS_EXT)).await
+    }
+
+    /// Sets the online status Ex: online, away, dnd, invisible
+    #[tokio::main]
+    pub async fn set_user_status(&self, status: &str) -> Result<UserStatus> {
+        let ext = format!("{}/status", USER_STATUS_EXT);
+        let request =
+            self.ocs_request(Method::PUT, &ext).form(&[("statusType", status)]);
+        Self::ocs_send(request).await
+    }
+
+    /// Sets a custom status message, cleared at the unix timestamp `clear_at` if supplied
+    #[tokio::main]
+    pub async fn set_user_status_message(
+        &self,
+        message: &str,
+        clear_at: Option<i64>,
+    ) -> Result<UserStatus> {
+        let ext = format!("{}/message/custom", USER_STATUS_EXT);
+        let mut params = vec![("message", message.to_string())];
+        if let Some(clear_at) = clear_at {
+            params.push(("clearAt", clear_at.to_string()));
+        }
+        let request = self.ocs_request(Method::PUT, &ext).form(&params);
+        Self::ocs_send(request).await
+    }
+
+    #[tokio::main]
+    pub async fn clear_user_status_message(&self) -> Result<()> {
+        let ext = format!("{}/message", USER_STATUS_EXT);
+        Self::ocs_send::<Value>(self.ocs_request(Method::DELETE, &ext)).await?;
+        Ok(())
+    }
+
+    /// Fetches status.php, it does not need authentication
+    #[tokio::main]
+    pub async fn get_server_status(&self) -> Result<ServerStatus> {
+        let url = format!("{}status.php", self.credentials.server);
+        let response =
+            self.client.get(&url).send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    #[tokio::main]
+    pub async fn get_capabilities(&self) -> Result<Capabilities> {
+        Self::ocs_send(
+            self.ocs_request(Method::GET, "ocs/v1.php/cloud/capabilities"),
+        )
+        .await
+    }
+
+    /// Creates a share, `params` are the form parameters of the OCS Share API
+    #[tokio::main]
+    pub async fn create_share(
+        &self,
+        params: &[(&str, String)],
+    ) -> Result<Share> {
+        let request = self.ocs_request(Method::POST, SHARES_EXT).form(params);
+        Self::ocs_send(request).await
+    }
+
+    /// Lists the shares of the user, only the shares of the path if supplied
+    #[tokio::main]
+    pub async fn get_shares(&self, path: Option<&Path>) -> Result<Vec<Share>> {
+        let mut request = self.ocs_request(Method::GET, SHARES_EXT);
+        if let Some(path) = path {
+            request = request.query(&[("path", path.to_string_lossy())]);
+        }
+        Self::ocs_send(request).await
+    }
+
+    /// Deletes a share, removing access for the recipients
+    #[tokio::main]
+    pub async fn delete_share(&self, id: &str) -> Result<()> {
+        let request = self.ocs_request_ids(Method::DELETE, SHARES_EXT, &[id]);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Lists the federated shares from other servers waiting to be accepted
+    #[tokio::main]
+    pub async fn get_pending_remote_shares(&self) -> Result<Vec<RemoteShare>> {
+        let ext = format!("{}/pending", REMOTE_SHARES_EXT);
+        Self::ocs_send(self.ocs_request(Method::GET, &ext)).await
+    }
+
+    /// Accepts a pending federated share
+    #[tokio::main]
+    pub async fn accept_remote_share(&self, id: &str) -> Result<()> {
+        let request = self.ocs_request_ids(
+            Method::POST,
+            REMOTE_SHARES_EXT,
+            &["pending", id],
         );
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
 
-        let response = self
-            .client
-            .get(&request)
-            .basic_auth(
-                &self.credentials.username,
-                Some(&self.credentials.password),
-            )
-            .send()
-            .await?
-            .error_for_status();
+    /// Declines a pending federated share
+    #[tokio::main]
+    pub async fn decline_remote_share(&self, id: &str) -> Result<()> {
+        let request = self.ocs_request_ids(
+            Method::DELETE,
+            REMOTE_SHARES_EXT,
+            &["pending", id],
+        );
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
 
-        Ok(response?.bytes().await?)
+    /// Lists the notifications of the user
+    #[tokio::main]
+    pub async fn get_notifications(&self) -> Result<Vec<Notification>> {
+        Self::ocs_send(self.ocs_request(Method::GET, NOTIFICATIONS_EXT)).await
     }
 
+    /// Dismisses a notification, or every notification if no id is supplied
     #[tokio::main]
-    pub async fn send_file(self, path: &Path, data: Bytes) -> Result<()> {
-        let request: String = format!(
-            "{url}{ext}{user}/{path}",
-            url = self.credentials.server,
-            ext = "remote.php/dav/files/",
-            user = self.credentials.username,
-            path = path.to_string_lossy()
+    pub async fn delete_notification(&self, id: Option<&str>) -> Result<()> {
+        let ids: Vec<&str> = id.into_iter().collect();
+        let request =
+            self.ocs_request_ids(Method::DELETE, NOTIFICATIONS_EXT, &ids);
+        Self::ocs_send::<Value>(request).await?;
+        Ok(())
+    }
+
+    /// Lists activities newest first, starting after the activity `since`,
+    /// only those about `file_id` if supplied
+    #[tokio::main]
+    pub async fn get_activities(
+        &self,
+        file_id: Option<&str>,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<Activity>> {
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(since) = since {
+            query.push(("since", since.to_string()));
+        }
+        let ext = match file_id {
+            Some(id) => {
+                query.push(("object_type", String::from("files")));
+                query.push(("object_id", id.to_string()));
+                format!("{}/filter", ACTIVITY_EXT)
+            }
+            None => String::from(ACTIVITY_EXT),
+        };
+
+        let response =
+            Self::send(self.ocs_request(Method::GET, &ext).query(&query))
+                .await?;
+        // The activity app responds with 304 once there are no more activities
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Vec::new());
+        }
+        ocs::parse(&response.text().await?)
+    }
+
+    /// Updates a single attribute of a share
+    #[tokio::main]
+    pub async fn update_share(
+        &self,
+        id: &str,
+        params: &[(&str, String)],
+    ) -> Result<Share> {
+        let request =
+            self.ocs_request_ids(Method::PUT, SHARES_EXT, &[id]).form(params);
+        Self::ocs_send(request).await
+    }
+
+    #[tokio::main]
+    pub async fn get_file(&self, path: &Path) -> Result<Bytes> {
+        let request: String = self.files_url(path);
+
+        let request = self.client.get(&request).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
         );
 
-        self.client
+        Ok(Self::send(request).await?.bytes().await?)
+    }
+
+    #[tokio::main]
+    pub async fn send_file(&self, path: &Path, data: Bytes) -> Result<()> {
+        let request: String = self.files_url(path);
+
+        let request = self
+            .client
             .put(&request)
             .basic_auth(
-                self.credentials.username,
-                Some(self.credentials.password),
+                &self.credentials.username,
+                Some(&self.credentials.password),
             )
             .header("OCS-APIRequest", "true")
-            .body(data)
-            .send()
-            .await?
-            .error_for_status()?;
+            .body(data);
+        Self::send(request).await?;
 
         Ok(())
     }
 
     #[tokio::main]
     pub async fn make_folder(self, path: &Path) -> Result<()> {
-        let request: String = format!(
-            "{url}{ext}{user}/{path}",
-            url = self.credentials.server,
-            ext = "remote.php/dav/files/",
-            user = self.credentials.username,
-            path = path.to_string_lossy()
-        );
+        let request: String = self.files_url(path);
 
-        self.client
+        let request = self
+            .client
             .request(Method::from_bytes(b"MKCOL").unwrap(), &request)
             .basic_auth(
                 self.credentials.username,
                 Some(self.credentials.password),
-            )
-            .send()
-            .await?
-            .error_for_status()?;
+            );
+        Self::send(request).await?;
 
         Ok(())
     }
 
     #[tokio::main]
     pub async fn delete(self, path: &Path) -> Result<()> {
-        let request: String = format!(
-            "{url}{ext}{user}/{path}",
-            url = self.credentials.server,
-            ext = "remote.php/dav/files/",
-            user = self.credentials.username,
-            path = path.to_string_lossy()
-        );
+        let request: String = self.files_url(path);
 
-        self.client
+        let request = self
+            .client
             .request(Method::from_bytes(b"DELETE").unwrap(), &request)
             .basic_auth(
                 self.credentials.username,
                 Some(self.credentials.password),
-            )
-            .send()
+            );
+        Self::send(request).await?;
+
+        Ok(())
+    }
+    /// Lists the entries of a directory, the first entry is the directory itself
+    #[tokio::main]
+    pub async fn get_list(&self, path: &Path) -> Result<Vec<Entry>> {
+        self.propfind(path, "1").await
+    }
+
+    /// Gets the properties of a single file or directory
+    #[tokio::main]
+    pub async fn get_entry(&self, path: &Path) -> Result<Entry> {
+        self.propfind(path, "0")
             .await?
-            .error_for_status()?;
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Server returned no properties"))
+    }
+
+    async fn propfind(&self, path: &Path, depth: &str) -> Result<Vec<Entry>> {
+        let request: String = self.files_url(path);
+
+        let data = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <d:propfind xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+      <d:prop>{props}</d:prop>
+    </d:propfind>",
+            props = dav::FILE_PROPS
+        );
+
+        self.dav_propfind(&request, &self.files_path(), depth, &data).await
+    }
+
+    /// Sends a PROPFIND to the url, `root` is the url path stripped from the entry paths
+    async fn dav_propfind(
+        &self,
+        url: &str,
+        root: &str,
+        depth: &str,
+        body: &str,
+    ) -> Result<Vec<Entry>> {
+        let xml = self.dav_xml("PROPFIND", url, Some(depth), body).await?;
+        dav::parse_multistatus(&xml, root)
+    }
+
+    /// Sends a dav request with an xml body and returns the xml response
+    async fn dav_xml(
+        &self,
+        method: &str,
+        url: &str,
+        depth: Option<&str>,
+        body: &str,
+    ) -> Result<String> {
+        let mut request = self
+            .client
+            .request(Method::from_bytes(method.as_bytes())?, url)
+            .basic_auth(
+                &self.credentials.username,
+                Some(&self.credentials.password),
+            )
+            .header("Content-Type", "application/xml")
+            .body(body.to_owned());
+        if let Some(depth) = depth {
+            request = request.header("depth", depth);
+        }
+
+        Ok(Self::send(request).await?.text().await?)
+    }
+
+    /// Url path of the files dav collection, used to strip hrefs
+    fn files_path(&self) -> String {
+        format!("{}{}", self.credentials.server.path(), self.files_ext)
+    }
+
+    /// Lists the files matching the filter rules of a filter-files REPORT, Ex: <oc:favorite>1</oc:favorite>
+    async fn filter_files(&self, rules: &str) -> Result<Vec<Entry>> {
+        let data = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <oc:filter-files xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+      <d:prop>{props}</d:prop>
+      <oc:filter-rules>{rules}</oc:filter-rules>
+    </oc:filter-files>",
+            props = dav::FILE_PROPS,
+            rules = rules
+        );
 
+        let url = self.files_url(Path::new(""));
+        let xml = self.dav_xml("REPORT", &url, None, &data).await?;
+        dav::parse_multistatus(&xml, &self.files_path())
+    }
+
+    /// Marks or unmarks a file or directory as favorite
+    #[tokio::main]
+    pub async fn set_favorite(
+        &self,
+        path: &Path,
+        favorite: bool,
+    ) -> Result<()> {
+        let data = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <d:propertyupdate xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+      <d:set><d:prop><oc:favorite>{}</oc:favorite></d:prop></d:set>
+    </d:propertyupdate>",
+            favorite as u8
+        );
+
+        let url = self.files_url(path);
+        // the response is a multistatus even when the property is not updated
+        let xml = self.dav_xml("PROPPATCH", &url, None, &data).await?;
+        dav::check_proppatch(&xml, "favorite")
+    }
+
+    /// Lists the favorite files and directories
+    #[tokio::main]
+    pub async fn get_favorites(&self) -> Result<Vec<Entry>> {
+        self.filter_files("<oc:favorite>1</oc:favorite>").await
+    }
+
+    /// Lists the files and directories with the system tag
+    #[tokio::main]
+    pub async fn get_tagged_files(&self, tag_id: &str) -> Result<Vec<Entry>> {
+        let rules = format!("<oc:systemtag>{}</oc:systemtag>", tag_id);
+        self.filter_files(&rules).await
+    }
+
+    /// Lists every system tag, or only the tags of a file if the oc:fileid is supplied
+    #[tokio::main]
+    pub async fn get_tags(&self, file_id: Option<&str>) -> Result<Vec<Tag>> {
+        static DATA: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <d:propfind xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+      <d:prop>
+        <oc:id/>
+        <oc:display-name/>
+        <oc:user-visible/>
+        <oc:user-assignable/>
+      </d:prop>
+    </d:propfind>";
+
+        let ext = match file_id {
+            Some(id) => format!("systemtags-relations/files/{}", id),
+            None => String::from("systemtags"),
+        };
+        let url = self.dav_url(&ext);
+        let xml = self.dav_xml("PROPFIND", &url, Some("1"), DATA).await?;
+        dav::parse_tags(&xml)
+    }
+
+    /// Creates a user visible and assignable system tag, returns the id of the new tag
+    #[tokio::main]
+    pub async fn create_tag(&self, name: &str) -> Result<String> {
+        let tag = serde_json::json!({
+            "name": name,
+            "userVisible": true,
+            "userAssignable": true,
+            "canAssign": true,
+        });
+
+        let request = self
+            .client
+            .post(self.dav_url("systemtags/"))
+            .basic_auth(
+                &self.credentials.username,
+                Some(&self.credentials.password),
+            )
+            .json(&tag);
+        let response = Self::send(request).await?;
+        Self::created_id(&response)
+    }
+
+    /// Assigns a system tag to a file, `file_id` is the oc:fileid of the file
+    #[tokio::main]
+    pub async fn tag_file(&self, file_id: &str, tag_id: &str) -> Result<()> {
+        let ext = format!("systemtags-relations/files/{}/{}", file_id, tag_id);
+        let request = self.client.put(self.dav_url(&ext)).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
+        );
+
+        let response = Self::check_available(request.send().await?)?;
+        // conflict means the tag was already assigned
+        if response.status() != StatusCode::CONFLICT {
+            response.error_for_status()?;
+        }
         Ok(())
     }
+
+    /// Removes a system tag from a file, `file_id` is the oc:fileid of the file
     #[tokio::main]
-    pub async fn get_list(self, path: &Path) -> Result<String> {
-        let request: String = format!(
-            "{url}{ext}{user}/{path}",
-            url = self.credentials.server,
-            ext = "remote.php/dav/files/",
-            user = self.credentials.username,
-            path = path.to_string_lossy()
+    pub async fn untag_file(&self, file_id: &str, tag_id: &str) -> Result<()> {
+        let ext = format!("systemtags-relations/files/{}/{}", file_id, tag_id);
+        let request = self.client.delete(self.dav_url(&ext)).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
+        );
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// Lists the comments on a file, `file_id` is the oc:fileid of the file
+    #[tokio::main]
+    pub async fn get_comments(
+        &self,
+        file_id: &str,
+        limit: usize,
+    ) -> Result<Vec<Comment>> {
+        let data = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <oc:filter-comments xmlns:d=\"DAV:\" xmlns:oc=\"http://owncloud.org/ns\">
+      <oc:limit>{}</oc:limit>
+      <oc:offset>0</oc:offset>
+    </oc:filter-comments>",
+            limit
         );
 
+        let url = self.dav_url(&format!("comments/files/{}", file_id));
+        let xml = self.dav_xml("REPORT", &url, None, &data).await?;
+        dav::parse_comments(&xml)
+    }
+
+    /// Adds a comment to a file, returns the id of the new comment
+    #[tokio::main]
+    pub async fn add_comment(
+        &self,
+        file_id: &str,
+        message: &str,
+    ) -> Result<String> {
+        let comment = serde_json::json!({
+            "actorType": "users",
+            "verb": "comment",
+            "message": message,
+            "objectType": "files",
+        });
+
+        let url = self.dav_url(&format!("comments/files/{}", file_id));
+        let request = self
+            .client
+            .post(url)
+            .basic_auth(
+                &self.credentials.username,
+                Some(&self.credentials.password),
+            )
+            .json(&comment);
+        let response = Self::send(request).await?;
+        Self::created_id(&response)
+    }
+
+    /// Deletes a comment, only the author of a comment can delete it
+    #[tokio::main]
+    pub async fn delete_comment(&self, file_id: &str, id: &str) -> Result<()> {
+        let url = self.dav_url_ids("comments/files", &[file_id, id]);
+        let request = self.client.delete(url).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
+        );
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// Gets the id of a created dav resource, the last segment of its url
+    fn created_id(response: &Response) -> Result<String> {
+        response
+            .headers()
+            .get("Content-Location")
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| l.trim_end_matches('/').rsplit('/').next())
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow!("Server did not return the id"))
+    }
+
+    /// Searches the files of the user below the path
+    #[tokio::main]
+    pub async fn search(
+        &self,
+        path: &Path,
+        query: &SearchQuery,
+    ) -> Result<Vec<Entry>> {
+        let files = format!("files/{}", self.credentials.username);
+        let scope =
+            format!("/{}/{}", files, path.to_string_lossy().trim_matches('/'));
+
+        let request = self
+            .client
+            .request(Method::from_bytes(b"SEARCH").unwrap(), self.dav_url(""))
+            .basic_auth(
+                &self.credentials.username,
+                Some(&self.credentials.password),
+            )
+            .header("Content-Type", "text/xml")
+            .body(query.to_xml(scope.trim_end_matches('/')));
+
+        let xml = Self::send(request).await?.text().await?;
+        dav::parse_multistatus(&xml, &self.dav_path(&files))
+    }
+
+    /// Lists the items in the trash bin
+    #[tokio::main]
+    pub async fn get_trash(&self) -> Result<Vec<Entry>> {
         static DATA: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
     <d:propfind xmlns:d=\"DAV:\">
-      <d:prop xmlns:oc=\"http://owncloud.org/ns\">
+      <d:prop xmlns:oc=\"http://owncloud.org/ns\" xmlns:nc=\"http://nextcloud.org/ns\">
+        <nc:trashbin-filename/>
+        <nc:trashbin-original-location/>
+        <nc:trashbin-deletion-time/>
+        <d:getcontentlength/>
+        <d:resourcetype/>
+        <oc:size/>
+      </d:prop>
+    </d:propfind>";
+
+        let ext = format!("trashbin/{}/trash", self.credentials.username);
+        let (url, root) = (self.dav_url(&ext), self.dav_path(&ext));
+        let entries = self.dav_propfind(&url, &root, "1", DATA).await?;
+        // the first entry is the trash bin itself
+        Ok(entries.into_iter().skip(1).collect())
+    }
+
+    /// Restores an item of the trash bin to its original location
+    #[tokio::main]
+    pub async fn restore_trash(&self, name: &str) -> Result<()> {
+        let user = self.credentials.username.as_str();
+        let source = self.dav_url_ids("trashbin", &[user, "trash", name]);
+        let destination =
+            self.dav_url_ids("trashbin", &[user, "restore", name]);
+
+        let request = self
+            .client
+            .request(Method::from_bytes(b"MOVE").unwrap(), &source)
+            .basic_auth(
+                &self.credentials.username,
+                Some(&self.credentials.password),
+            )
+            .header("Destination", destination);
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// Permanently deletes an item of the trash bin, or the whole trash bin if no item is supplied
+    #[tokio::main]
+    pub async fn delete_trash(&self, name: Option<&str>) -> Result<()> {
+        let mut ids = vec![self.credentials.username.as_str(), "trash"];
+        ids.extend(name);
+
+        let url = self.dav_url_ids("trashbin", &ids);
+        let request = self.client.delete(url).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
+        );
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    /// Lists the previous versions of a file, `file_id` is the oc:fileid of the file
+    #[tokio::main]
+    pub async fn get_versions(&self, file_id: &str) -> Result<Vec<Entry>> {
+        static DATA: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+    <d:propfind xmlns:d=\"DAV:\">
+      <d:prop>
         <d:getlastmodified/>
         <d:getcontentlength/>
         <d:getcontenttype/>
-        <oc:permissions/>
         <d:resourcetype/>
-        <d:getetag/>
       </d:prop>
     </d:propfind>";
 
-        let response = self
+        let ext = self.versions_ext(file_id);
+        let (url, root) = (self.dav_url(&ext), self.dav_path(&ext));
+        let entries = self.dav_propfind(&url, &root, "1", DATA).await?;
+        // the first entry is the version collection itself
+        Ok(entries.into_iter().skip(1).collect())
+    }
+
+    /// Downloads a previous version of a file
+    #[tokio::main]
+    pub async fn get_version(
+        &self,
+        file_id: &str,
+        version: &str,
+    ) -> Result<Bytes> {
+        let url = self.version_url(file_id, version);
+        let request = self.client.get(url).basic_auth(
+            &self.credentials.username,
+            Some(&self.credentials.password),
+        );
+        Ok(Self::send(request).await?.bytes().await?)
+    }
+
+    /// Restores a previous version of a file, the current version is kept as a version
+    #[tokio::main]
+    pub async fn restore_version(
+        &self,
+        file_id: &str,
+        version: &str,
+    ) -> Result<()> {
+        let source = self.version_url(file_id, version);
+        let destination = self.dav_url(&format!(
+            "versions/{}/restore/target",
+            self.credentials.username
+        ));
+
+        let request = self
             .client
-            .request(Method::from_bytes(b"PROPFIND").unwrap(), &request)
+            .request(Method::from_bytes(b"MOVE").unwrap(), &source)
             .basic_auth(
-                self.credentials.username,
-                Some(self.credentials.password),
+                &self.credentials.username,
+                Some(&self.credentials.password),
             )
-            .header("depth", "1")
-            .body(DATA)
-            .send()
-            .await?
-            .error_for_status();
+            .header("Destination", destination);
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    fn versions_ext(&self, file_id: &str) -> String {
+        format!("versions/{}/versions/{}", self.credentials.username, file_id)
+    }
+
+    fn version_url(&self, file_id: &str, version: &str) -> String {
+        let user = self.credentials.username.as_str();
+        self.dav_url_ids("versions", &[user, "versions", file_id, version])
+    }
+
+    /// Url of a resource of the dav endpoint, Ex: trashbin/user/trash
+    fn dav_url(&self, ext: &str) -> String {
+        format!("{}remote.php/dav/{}", self.credentials.server, ext)
+    }
+
+    /// Url of a resource of the dav endpoint with `ids` appended as percent-encoded path segments
+    fn dav_url_ids(&self, ext: &str, ids: &[&str]) -> String {
+        self.server_url(&format!("remote.php/dav/{}", ext), ids).to_string()
+    }
 
-        Ok(response?.text().await?)
+    /// Url of the endpoint on the server, every one of `ids` is a single path segment
+    fn server_url(&self, ext: &str, ids: &[&str]) -> Url {
+        let mut url = self.credentials.server.clone();
+        url.path_segments_mut()
+            .expect("The server is an http url")
+            .pop_if_empty()
+            .extend(ext.split('/'))
+            .extend(ids);
+        url
+    }
+
+    /// Url path of a resource of the dav endpoint, used to strip hrefs
+    fn dav_path(&self, ext: &str) -> String {
+        format!("{}remote.php/dav/{}", self.credentials.server.path(), ext)
     }
 }
 
@@ -224,4 +1063,28 @@ mod tests {
         .to_http();
         http.get_user().expect_err("Username is invalid should fail");
     }
+
+    #[test]
+    fn ocs_request_ids_encoded() {
+        let url = Url::parse("https://cloud.example.com/nextcloud/").unwrap();
+        let http = Credentials::new("test", "pass", url).to_http();
+        let request = http
+            .ocs_request_ids(Method::GET, USERS_EXT, &["a b/../c?", "groups"])
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().path(),
+            "/nextcloud/ocs/v1.php/cloud/users/a%20b%2F..%2Fc%3F/groups"
+        );
+    }
+
+    #[test]
+    fn dav_url_ids_encoded() {
+        let url = Url::parse("https://cloud.example.com/").unwrap();
+        let http = Credentials::new("test", "pass", url).to_http();
+        assert_eq!(
+            http.dav_url_ids("trashbin", &["test", "trash", "a #1.txt.d1612345"]),
+            "https://cloud.example.com/remote.php/dav/trashbin/test/trash/a%20%231.txt.d1612345"
+        );
+    }
 }
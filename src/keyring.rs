@@ -1,31 +1,46 @@
 use super::Credentials;
+use age::secrecy::SecretString;
 use anyhow::Result;
-use base64::{decode, encode};
 use keyring::Keyring;
+use log::{info, warn};
 
 const SERVICE_NAME: &str = "nextcloud_client_cli";
 
 impl Credentials {
     pub fn write(&self) -> Result<()> {
         let keyring = Keyring::new(SERVICE_NAME, "username");
-        let credentials_string =
-            format!("{} {} {}", self.username, self.password, self.server);
-        let content = encode(credentials_string);
+        let content = self.serialize()?;
         if keyring.set_password(&content).is_err() {
-            self.file_write_default()?;
+            return self.file_write_default();
         }
 
-        Ok(())
+        // a file from an earlier login without a keyring would keep the old password on disk
+        Credentials::file_delete_default()
+    }
+
+    /// Stores the credentials in a passphrase encrypted file instead of the keyring
+    pub fn write_encrypted(&self, passphrase: SecretString) -> Result<()> {
+        let keyring = Keyring::new(SERVICE_NAME, "username");
+        // a stale keyring entry would take precedence over the file
+        let _ = keyring.delete_password();
+        self.file_write_encrypted_default(passphrase)
     }
 
     pub fn read() -> Result<Self> {
         let keyring = Keyring::new(SERVICE_NAME, "username");
         if let Ok(content) = keyring.get_password() {
-            let data = String::from_utf8_lossy(&decode(content)?).to_string();
-
-            let v: Vec<&str> = data.split(' ').collect();
-
-            Ok(Self::from(v[0], v[1], v[2])?)
+            let (creds, legacy) = Self::deserialize(&content)?;
+            if legacy {
+                info!("Migrating keyring credentials to the new format");
+                // the legacy credentials are still usable if they can not be rewritten
+                if let Err(e) = creds
+                    .serialize()
+                    .and_then(|c| Ok(keyring.set_password(&c)?))
+                {
+                    warn!("Unable to migrate keyring credentials: {}", e);
+                }
+            }
+            Ok(creds)
         } else {
             Credentials::file_read_default()
         }
@@ -33,10 +48,8 @@ impl Credentials {
 
     pub fn delete() -> Result<()> {
         let keyring = Keyring::new(SERVICE_NAME, "username");
-        if keyring.delete_password().is_err() {
-            Credentials::file_delete_default()?;
-        }
-        Ok(())
+        let _ = keyring.delete_password();
+        Credentials::file_delete_default()
     }
 }
 
@@ -1,25 +1,33 @@
 use anyhow::anyhow;
 use bytes::Bytes;
+use chrono::NaiveDate;
 use clap::AppSettings;
 use log::{error, info, warn};
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 use url::{ParseError, Url};
-use xmltree::Element;
 
+mod dav;
 mod file;
 mod http;
 mod keyring;
+mod ocs;
+mod output;
 mod util;
 
-//// Structure for storing user credentials
+/// Structure for storing user credentials
 #[derive(Debug, Clone)]
 pub struct Credentials {
     pub username: String,
     pub password: String,
     pub server: Url,
+    /// Unix timestamp of when the credentials were first stored, None if they never were
+    created: Option<u64>,
 }
 
 impl Credentials {
@@ -45,7 +53,7 @@ impl Credentials {
             if server.contains("https://") || server.contains("http://") {
                 String::from(server)
             } else {
-                format!("https://{}", server.to_string())
+                format!("https://{}", server)
             };
 
         let url: Url = Url::parse(&fqdn)?;
@@ -53,6 +61,7 @@ impl Credentials {
             username: username.as_ref().to_string(),
             password: password.as_ref().to_string(),
             server: url,
+            created: None,
         })
     }
 
@@ -61,12 +70,70 @@ impl Credentials {
             username: username.as_ref().to_string(),
             password: password.as_ref().to_string(),
             server,
+            created: None,
         }
     }
 
-    fn display(&self) -> String {
-        format!("{} {} {}", self.username, self.password, self.server)
+    /// Serializes the credentials into the current versioned storage format
+    fn serialize(&self) -> anyhow::Result<String> {
+        let created = match self.created {
+            Some(created) => created,
+            None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        };
+        let record = CredentialsRecord {
+            version: CREDENTIALS_VERSION,
+            profile: ProfileRecord { name: String::from("default"), created },
+            username: self.username.clone(),
+            password: self.password.clone(),
+            server: self.server.to_string(),
+        };
+        Ok(serde_json::to_string(&record)?)
     }
+
+    /// Deserializes stored credentials, returns true as the second value if the data used the legacy format and should be rewritten
+    fn deserialize(data: &str) -> anyhow::Result<(Self, bool)> {
+        if data.trim_start().starts_with('{') {
+            let record: CredentialsRecord = serde_json::from_str(data)?;
+            if record.version > CREDENTIALS_VERSION {
+                return Err(anyhow!(
+                    "Credentials were stored by a newer version of nxcloud"
+                ));
+            }
+            let mut creds =
+                Self::from(record.username, record.password, record.server)?;
+            creds.created = Some(record.profile.created);
+            return Ok((creds, false));
+        }
+
+        // legacy format, base64 of the space joined username, password and server
+        let decoded = String::from_utf8(base64::decode(data.trim())?)?;
+        let v: Vec<&str> = decoded.split(' ').collect();
+        if v.len() != 3 {
+            return Err(anyhow!("Unexpected credentials format"));
+        }
+        Ok((Self::from(v[0], v[1], v[2])?, true))
+    }
+}
+
+/// Version of the credentials storage format
+const CREDENTIALS_VERSION: u32 = 1;
+
+/// Storage format of the credentials in the keyring or credentials file
+#[derive(Serialize, Deserialize)]
+struct CredentialsRecord {
+    version: u32,
+    profile: ProfileRecord,
+    username: String,
+    password: String,
+    server: String,
+}
+
+/// Metadata about the stored login
+#[derive(Serialize, Deserialize)]
+struct ProfileRecord {
+    name: String,
+    /// Unix timestamp of when the credentials were stored
+    created: u64,
 }
 
 /// Cli Enum for command parsing
@@ -81,6 +148,16 @@ struct Opt {
     #[structopt(short, long, parse(from_occurrences))]
     verbose: u8,
 
+    /// Output format of the results: text, json or csv.
+    #[structopt(
+        short,
+        long,
+        global = true,
+        default_value = "text",
+        possible_values = &["text", "json", "csv"]
+    )]
+    output: output::Format,
+
     #[structopt(subcommand)] // Note that we mark a field as a subcommand
     cmd: Command,
 }
@@ -93,7 +170,14 @@ struct Opt {
 enum Command {
     /// Display's the account status.
     #[structopt(name = "status")]
-    Status {},
+    Status {
+        /// Contact the server and display the full user info.
+        #[structopt(short, long)]
+        remote: bool,
+    },
+    /// Display's the full user info of the logged in user from the server.
+    #[structopt(name = "whoami")]
+    Whoami,
     #[structopt(name = "login")]
     /// Login to your NextCloud server, please provide a app password for security.
     Login {
@@ -106,9 +190,19 @@ enum Command {
         /// A NextCloud app password, do not use your account password.
         #[structopt()]
         password: String,
+        /// Store the credentials in a passphrase encrypted file instead of the keyring.
+        #[structopt(short, long)]
+        encrypt: bool,
     },
     /// Logout of your NextCloud server.
-    Logout,
+    Logout {
+        /// Also revoke the app password on the server.
+        #[structopt(short, long)]
+        revoke: bool,
+    },
+    /// Create and revoke app passwords.
+    #[structopt(name = "app-password")]
+    AppPassword(AppPasswordCommand),
     /// Push a file from your local machine to the server.
     #[structopt(name = "push")]
     Push {
@@ -130,6 +224,37 @@ enum Command {
         destination: PathBuf,
     },
 
+    /// Pull a file or directory from a public share link, no login required.
+    #[structopt(name = "pull-public")]
+    PullPublic {
+        /// Public share link, Ex: https://cloud.example.com/s/TOKEN.
+        #[structopt(parse(try_from_str = parse_url))]
+        link: Url,
+        /// Path to destination file or directory.
+        #[structopt(parse(from_os_str))]
+        destination: Option<PathBuf>,
+        /// Password of the share if it is protected.
+        #[structopt(short, long)]
+        password: Option<String>,
+        /// Only list the shared files instead of downloading them.
+        #[structopt(short, long)]
+        list: bool,
+    },
+
+    /// Push files into a public share link that allows uploads, no login required.
+    #[structopt(name = "push-public")]
+    PushPublic {
+        /// Public share link, Ex: https://cloud.example.com/s/TOKEN.
+        #[structopt(parse(try_from_str = parse_url))]
+        link: Url,
+        /// Paths to source files.
+        #[structopt(parse(from_os_str), required = true)]
+        sources: Vec<PathBuf>,
+        /// Password of the share if it is protected.
+        #[structopt(short, long)]
+        password: Option<String>,
+    },
+
     /// List files and directories.
     #[structopt(name = "ls")]
     Ls {
@@ -144,6 +269,74 @@ enum Command {
         all: bool,
     },
 
+    /// Display the storage quota and usage of each top level folder.
+    #[structopt(name = "df")]
+    Df {
+        /// Print sizes in human readable format.
+        #[structopt(short, long)]
+        human_readable: bool,
+    },
+
+    /// Display the disk usage of a directory and its subdirectories.
+    #[structopt(name = "du")]
+    Du {
+        /// Path to directory.
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+
+        /// Only display the total of the directory.
+        #[structopt(short, long)]
+        summarize: bool,
+
+        /// Print sizes in human readable format.
+        #[structopt(short, long)]
+        human_readable: bool,
+
+        /// Only display directories this many levels below the directory.
+        #[structopt(short = "d", long)]
+        max_depth: Option<usize>,
+    },
+
+    /// Search for files and directories on the server.
+    #[structopt(name = "search", alias = "find")]
+    Search {
+        /// Directory to search in.
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+
+        /// Name pattern, * matches any characters and ? a single character.
+        #[structopt(short, long)]
+        name: Option<String>,
+
+        /// Mime type pattern, Ex: image/*.
+        #[structopt(short, long)]
+        mime: Option<String>,
+
+        /// Minimum size, Ex: 10M.
+        #[structopt(long, parse(try_from_str = util::parse_size))]
+        min_size: Option<i64>,
+
+        /// Maximum size, Ex: 1G.
+        #[structopt(long, parse(try_from_str = util::parse_size))]
+        max_size: Option<i64>,
+
+        /// Only modified on or after the date, Ex: 2021-01-31.
+        #[structopt(long, parse(try_from_str = parse_date))]
+        newer: Option<NaiveDate>,
+
+        /// Only modified before the date, Ex: 2021-12-31.
+        #[structopt(long, parse(try_from_str = parse_date))]
+        older: Option<NaiveDate>,
+
+        /// Maximum number of results.
+        #[structopt(long)]
+        limit: Option<usize>,
+
+        /// Display size and modification time.
+        #[structopt(short, long)]
+        list: bool,
+    },
+
     /// Make a directory.
     #[structopt(name = "mkdir")]
     Mkdir {
@@ -164,225 +357,2332 @@ enum Command {
         force: bool,
     },
 
-    /// Enter an interactive prompt.
-    #[structopt(name = "shell")]
-    Shell {},
+    /// Administrate the server, needs admin rights.
+    #[structopt(name = "admin")]
+    Admin(AdminCommand),
 
-    /// Change directory of remote - Shell Only.
-    #[structopt(name = "cd")]
-    Cd {
-        /// directory to change to.
+    /// Browse, restore and purge deleted files.
+    #[structopt(name = "trash")]
+    Trash(TrashCommand),
+
+    /// List, pull and restore previous versions of a file.
+    #[structopt(name = "versions")]
+    Versions {
+        /// Path to file to list the versions of.
         #[structopt(parse(from_os_str))]
-        path: PathBuf,
+        path: Option<PathBuf>,
+
+        #[structopt(subcommand)]
+        cmd: Option<VersionsCommand>,
     },
-}
 
-/// Entrypoint of the program, returns 0 on success
-fn main() -> anyhow::Result<()> {
-    //Command::clap().gen_completions(env!("CARGO_PKG_NAME"), Shell::Bash, "target");
-    let current_dir = PathBuf::from("/");
+    /// Mark files and directories as favorite.
+    #[structopt(name = "fav")]
+    Fav(FavCommand),
 
-    let cli = Opt::from_args();
+    /// Tag files and directories with system tags.
+    #[structopt(name = "tag")]
+    Tag(TagCommand),
 
-    // Sets the log level
-    match cli.verbose {
-        0 => env_logger::builder()
-            .filter_level(log::LevelFilter::Warn)
-            .format_timestamp(None)
-            .init(),
-        1 => env_logger::builder()
-            .filter_level(log::LevelFilter::Info)
-            .format_timestamp(None)
-            .init(),
-        2 => env_logger::builder()
-            .filter_level(log::LevelFilter::Debug)
-            .format_timestamp(None)
-            .init(),
-        _ => env_logger::builder()
-            .filter_level(log::LevelFilter::Trace)
-            .format_timestamp(None)
-            .init(),
-    };
+    /// Add, list and remove comments on files.
+    #[structopt(name = "comment")]
+    Comment(CommentCommand),
 
-    info!("Logger has been initialized");
+    /// Show whether the server is installed, in maintenance and its version, no login needed.
+    /// Exits with 1 if the server is not usable, 2 if it can not be reached and 3 on any other failure.
+    #[structopt(name = "server-status")]
+    ServerStatus {
+        /// Server url, defaults to the server logged in to.
+        #[structopt(parse(try_from_str = parse_url))]
+        server: Option<Url>,
+    },
 
-    run(cli, current_dir)?;
+    /// Measure the latency of WebDAV requests to the server.
+    /// Exits with 1 if the server is in maintenance or slow, 2 if a request fails and 3 on any other failure.
+    #[structopt(name = "ping")]
+    Ping {
+        /// Number of requests to send.
+        #[structopt(short, long, default_value = "3")]
+        count: u32,
 
-    Ok(())
-}
+        /// Latency in milliseconds above which the server is considered slow.
+        #[structopt(short, long, default_value = "2000")]
+        warning: u128,
+    },
 
-fn run(cli: Opt, mut current_dir: PathBuf) -> anyhow::Result<PathBuf> {
-    match cli.cmd {
-        Command::Status {} => status(),
-        Command::Login { server, username, password } => {
-            login(server, username, password)?
-        }
-        Command::Logout {} => logout()?,
-        Command::Push { source, destination } => push(
-            source,
-            util::join_dedot_path(current_dir.clone(), destination)?,
-        )?,
-        Command::Pull { source, destination } => pull(
-            util::join_dedot_path(current_dir.clone(), source)?,
-            destination,
-        )?,
-        Command::Ls { path, list, all } => {
-            let fp = if path.is_none() {
-                current_dir.clone()
-            } else {
-                util::join_dedot_path(current_dir.clone(), path.unwrap())?
-            };
-            ls(fp, list, all)?;
-        }
-        Command::Mkdir { path } => {
-            mkdir(util::join_dedot_path(current_dir.clone(), path)?)?
-        }
-        Command::Rm { path, force } => {
-            rm(util::join_dedot_path(current_dir.clone(), path)?, force)?
-        }
-        Command::Shell {} => shell(current_dir.clone())?,
-        Command::Cd { path } => {
-            current_dir = util::join_dedot_path(current_dir.clone(), path)?
-        }
-    };
-    Ok(current_dir)
-}
+    /// Show the capabilities of the server.
+    #[structopt(name = "capabilities")]
+    Capabilities {
+        /// Fetch the capabilities again instead of using the cached ones.
+        #[structopt(short, long)]
+        refresh: bool,
 
-/// Login to the nextcloud server
-fn login(
-    server: Url,
-    username: String,
-    password: String,
-) -> anyhow::Result<()> {
-    let creds = Credentials::new(username, password, server);
+        /// Print the capabilities of every app as json.
+        #[structopt(short, long)]
+        all: bool,
+    },
 
-    let http = creds.clone().to_http();
-    http.get_user()?;
-    creds.write()?;
+    /// Show who created, changed, shared or deleted what and when.
+    #[structopt(name = "activity")]
+    Activity {
+        /// Only activities about this file or directory.
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
 
-    println!("Login successful");
-    Ok(())
-}
+        /// Only activities of this type, Ex: file_created, file_changed, file_deleted, shared.
+        #[structopt(short = "t", long = "type")]
+        activity_type: Option<String>,
 
-/// Logout of the nextcloud server
-fn logout() -> anyhow::Result<()> {
-    match Credentials::delete() {
-        Ok(_) => println!("Logout Successful"),
-        Err(_) => return Err(anyhow!("Logout Failed")),
-    }
+        /// Only activities on or after the date, Ex: 2021-01-31.
+        #[structopt(long, parse(try_from_str = parse_date))]
+        since: Option<NaiveDate>,
 
-    Ok(())
-}
+        /// Only activities before the date, Ex: 2021-12-31.
+        #[structopt(long, parse(try_from_str = parse_date))]
+        until: Option<NaiveDate>,
 
-/// Prints the username and server of logged in user
-fn status() {
-    match Credentials::read() {
-        Ok(creds) => {
-            let username: String = creds.username;
-            let server: Url = creds.server;
-            println!(
-                "Logged in to Server: '{}' as User: '{}'",
-                server, username
-            );
-        }
-        Err(_) => println!("Not logged in"),
-    }
-}
+        /// Maximum number of activities.
+        #[structopt(long, default_value = "50")]
+        limit: usize,
+    },
 
-/// lists files
-fn ls(path: PathBuf, list: bool, all: bool) -> anyhow::Result<()> {
-    // TODO fix this garbadge lol
+    /// Show or set your online status and status message.
+    #[structopt(name = "user-status")]
+    UserStatus(UserStatusCommand),
 
-    let creds = Credentials::read()?;
-    let http = creds.to_http();
-    let data: String = http.get_list(&path)?;
-    let xml = Element::parse(data.as_bytes()).unwrap();
-    let items = xml.children;
-    let mut files: Vec<String> = vec![];
-    let mut full_path: Option<String> = None;
-    for i in items {
-        let resp = i.as_element().unwrap().to_owned().children;
-        let file = resp[0].clone().as_element().unwrap().to_owned().children[0]
-            .clone()
-            .as_text()
-            .unwrap()
-            .to_owned();
-        if full_path.is_none() {
-            full_path = Some(file);
-        } else {
-            let a = full_path.clone().unwrap();
-            let new_name = file.replace(&a, "").replace("%20", " ");
-            if new_name.contains(' ') {
-                files.push("'".to_owned() + &new_name + "'")
-            } else if !new_name.starts_with('.') || all {
-                files.push(new_name);
-            }
-        }
-    }
-    let print: String = if list { files.join("\n") } else { files.join("  ") };
-    println!("{}", print);
+    /// List and dismiss your notifications.
+    #[structopt(name = "notifications")]
+    Notifications {
+        #[structopt(subcommand)]
+        cmd: Option<NotificationsCommand>,
 
-    Ok(())
+        #[structopt(flatten)]
+        follow: FollowOpt,
+    },
+
+    /// Share files and directories.
+    #[structopt(name = "share")]
+    Share(ShareCommand),
+
+    /// Enter an interactive prompt.
+    #[structopt(name = "shell")]
+    Shell {},
+
+    /// Change directory of remote - Shell Only.
+    #[structopt(name = "cd")]
+    Cd {
+        /// directory to change to.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
 }
 
-fn mkdir(path: PathBuf) -> anyhow::Result<()> {
-    let creds = Credentials::read()?;
-    creds.to_http().make_folder(&path)?;
-    Ok(())
+/// App password subcommands
+#[derive(Debug, StructOpt)]
+enum AppPasswordCommand {
+    /// Create a new app password, prompts for your account password.
+    #[structopt(name = "create")]
+    Create {
+        /// The server url, defaults to the server logged in to, Ex: https://cloud.example.com.
+        #[structopt(parse(try_from_str = parse_url))]
+        server: Option<Url>,
+        /// Your NextCloud username, defaults to the user logged in as.
+        username: Option<String>,
+    },
+    /// Revoke the app password you are logged in with and logout.
+    #[structopt(name = "revoke")]
+    Revoke {
+        /// Force revoke, will not show warning.
+        #[structopt(short, long)]
+        force: bool,
+    },
 }
 
-fn rm(path: PathBuf, force: bool) -> anyhow::Result<()> {
-    if path.to_string_lossy() == "/" {
-        error!("Deleting the root is not supported");
-        return Ok(());
-    }
+/// Admin subcommands
+#[derive(Debug, StructOpt)]
+enum AdminCommand {
+    /// Manage users.
+    #[structopt(name = "user")]
+    User(AdminUserCommand),
+    /// Manage groups.
+    #[structopt(name = "group")]
+    Group(AdminGroupCommand),
+    /// Manage apps.
+    #[structopt(name = "app")]
+    App(AdminAppCommand),
+}
 
-    let warning = format!(
-        "Are you sure you want to delete '{}', (y/n)",
-        path.to_string_lossy()
-    );
+/// User administration subcommands
+#[derive(Debug, StructOpt)]
+enum AdminUserCommand {
+    /// List users.
+    #[structopt(name = "list")]
+    List {
+        /// Only users whose id, name or email contains the text.
+        #[structopt(short, long)]
+        search: Option<String>,
 
-    if !force {
-        warn!("DIRECTORIES DELETE ALL FILES AND DIRECTORIES RECURSIVELY");
-        if !util::get_confirmation(&warning)? {
-            return Ok(());
-        }
-    }
+        /// Maximum number of users.
+        #[structopt(long)]
+        limit: Option<usize>,
 
-    let creds = Credentials::read()?;
+        /// Display name, email, groups and quota of the users.
+        #[structopt(short, long)]
+        long: bool,
+    },
+    /// Create a user, without a password a welcome email is sent to set one.
+    #[structopt(name = "create")]
+    Create {
+        /// Id of the new user.
+        id: String,
 
-    let http = creds.to_http();
-    http.delete(&path)?;
-    Ok(())
-}
+        /// Password of the new user.
+        #[structopt(short, long)]
+        password: Option<String>,
 
-/// Pulls a file from the server to your computer
-fn pull(source: PathBuf, destination: PathBuf) -> anyhow::Result<()> {
-    let creds = Credentials::read()?;
-    let http = creds.to_http();
+        /// Display name of the new user.
+        #[structopt(short, long)]
+        display_name: Option<String>,
 
-    let new_dest = util::format_destination_pull(&source, &destination)?;
-    let new_src = util::format_source_pull(&source)?;
+        /// Email of the new user.
+        #[structopt(short, long)]
+        email: Option<String>,
 
-    let data: Bytes = http.get_file(&new_src)?;
-    file::create_file(&new_dest, &data)?;
+        /// Group to add the new user to, can be repeated.
+        #[structopt(short, long = "group")]
+        groups: Vec<String>,
 
-    println!("Pulled {:?}, {:?}", new_src, new_dest);
-    Ok(())
+        /// Quota of the new user, Ex: 10G or none.
+        #[structopt(short, long, parse(try_from_str = parse_quota))]
+        quota: Option<String>,
+    },
+    /// Disable a user, they can no longer login.
+    #[structopt(name = "disable")]
+    Disable {
+        /// Id of the user.
+        id: String,
+    },
+    /// Enable a disabled user.
+    #[structopt(name = "enable")]
+    Enable {
+        /// Id of the user.
+        id: String,
+    },
+    /// Delete a user and all their files.
+    #[structopt(name = "delete")]
+    Delete {
+        /// Id of the user.
+        id: String,
+
+        /// Force delete, will not show warning.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Set the storage quota of a user.
+    #[structopt(name = "quota")]
+    Quota {
+        /// Id of the user.
+        id: String,
+
+        /// Quota, Ex: 10G, none for unlimited or default.
+        #[structopt(parse(try_from_str = parse_quota))]
+        quota: String,
+    },
+    /// Add a user to a group.
+    #[structopt(name = "add-group")]
+    AddGroup {
+        /// Id of the user.
+        id: String,
+        /// Id of the group.
+        group: String,
+    },
+    /// Remove a user from a group.
+    #[structopt(name = "remove-group")]
+    RemoveGroup {
+        /// Id of the user.
+        id: String,
+        /// Id of the group.
+        group: String,
+    },
+    /// Send the welcome email to a user again.
+    #[structopt(name = "resend-welcome")]
+    ResendWelcome {
+        /// Id of the user.
+        id: String,
+    },
 }
 
-/// Pushes a file from your computer to the server
-fn push(source: PathBuf, destination: PathBuf) -> anyhow::Result<()> {
-    let creds = Credentials::read()?;
-    let http = creds.to_http();
+/// Group administration subcommands
+#[derive(Debug, StructOpt)]
+enum AdminGroupCommand {
+    /// List groups.
+    #[structopt(name = "list")]
+    List {
+        /// Only groups whose id contains the text.
+        #[structopt(short, long)]
+        search: Option<String>,
 
-    let data: Bytes = file::read_file(&source)?;
-    let new_dest = util::format_destination_push(&source, &destination)?;
+        /// Maximum number of groups.
+        #[structopt(long)]
+        limit: Option<usize>,
+    },
+    /// Create a group.
+    #[structopt(name = "create")]
+    Create {
+        /// Id of the new group.
+        id: String,
+    },
+    /// Delete a group, its members are kept.
+    #[structopt(name = "delete")]
+    Delete {
+        /// Id of the group.
+        id: String,
+    },
+    /// List the members of a group.
+    #[structopt(name = "members")]
+    Members {
+        /// Id of the group.
+        id: String,
+    },
+    /// List the users that can administrate a group.
+    #[structopt(name = "subadmins")]
+    Subadmins {
+        /// Id of the group.
+        id: String,
+    },
+}
 
-    http.send_file(&new_dest, data)?;
+/// App administration subcommands
+#[derive(Debug, StructOpt)]
+enum AdminAppCommand {
+    /// List installed apps.
+    #[structopt(name = "list")]
+    List {
+        /// Only enabled apps.
+        #[structopt(long, conflicts_with = "disabled")]
+        enabled: bool,
 
-    println!("Push {:?}, {:?}", source, new_dest);
-    Ok(())
+        /// Only disabled apps.
+        #[structopt(long)]
+        disabled: bool,
+    },
+    /// Enable an app.
+    #[structopt(name = "enable")]
+    Enable {
+        /// Id of the app.
+        id: String,
+    },
+    /// Disable an app.
+    #[structopt(name = "disable")]
+    Disable {
+        /// Id of the app.
+        id: String,
+    },
+}
+
+/// Trash bin subcommands
+#[derive(Debug, StructOpt)]
+enum TrashCommand {
+    /// List the items in the trash bin.
+    #[structopt(name = "list")]
+    List,
+    /// Restore items to their original location.
+    #[structopt(name = "restore")]
+    Restore {
+        /// Trash item names or original locations of the items.
+        #[structopt(required = true)]
+        items: Vec<String>,
+    },
+    /// Permanently delete items from the trash bin.
+    #[structopt(name = "purge")]
+    Purge {
+        /// Trash item names or original locations of the items.
+        #[structopt(required = true)]
+        items: Vec<String>,
+
+        /// Force delete, will not show warning.
+        #[structopt(short, long)]
+        force: bool,
+    },
+    /// Permanently delete every item in the trash bin.
+    #[structopt(name = "empty")]
+    Empty {
+        /// Force delete, will not show warning.
+        #[structopt(short, long)]
+        force: bool,
+    },
+}
+
+/// File version subcommands
+#[derive(Debug, StructOpt)]
+enum VersionsCommand {
+    /// List the previous versions of a file, like versions <path> for files named like a subcommand.
+    #[structopt(name = "list")]
+    List {
+        /// Path to file.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Pull a previous version of a file to your local machine.
+    #[structopt(name = "pull")]
+    Pull {
+        /// Path to file.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Version to pull, as shown by the versions list.
+        version: String,
+        /// Path to destination file.
+        #[structopt(parse(from_os_str), default_value = ".")]
+        destination: PathBuf,
+    },
+    /// Restore a previous version of a file.
+    #[structopt(name = "restore")]
+    Restore {
+        /// Path to file.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Version to restore, as shown by the versions list.
+        version: String,
+    },
+}
+
+/// Favorite subcommands
+#[derive(Debug, StructOpt)]
+enum FavCommand {
+    /// Mark a file or directory as favorite.
+    #[structopt(name = "add")]
+    Add {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Remove a file or directory from the favorites.
+    #[structopt(name = "rm")]
+    Rm {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// List the favorite files and directories.
+    #[structopt(name = "ls")]
+    Ls,
+}
+
+/// System tag subcommands
+#[derive(Debug, StructOpt)]
+enum TagCommand {
+    /// Assign a tag to a file or directory, the tag is created if it does not exist.
+    #[structopt(name = "add")]
+    Add {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Name of the tag.
+        tag: String,
+    },
+    /// Remove a tag from a file or directory.
+    #[structopt(name = "rm")]
+    Rm {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Name of the tag.
+        tag: String,
+    },
+    /// List every tag, or the tags of a file or directory.
+    #[structopt(name = "ls")]
+    Ls {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+    },
+    /// List the files and directories with a tag.
+    #[structopt(name = "find")]
+    Find {
+        /// Name of the tag.
+        tag: String,
+    },
+}
+
+/// Comment subcommands
+#[derive(Debug, StructOpt)]
+enum CommentCommand {
+    /// Add a comment to a file or directory.
+    #[structopt(name = "add")]
+    Add {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Comment message.
+        message: String,
+    },
+    /// List the comments on a file or directory.
+    #[structopt(name = "ls")]
+    Ls {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Maximum number of comments, newest first.
+        #[structopt(long, default_value = "100")]
+        limit: usize,
+    },
+    /// Remove one of your comments from a file or directory.
+    #[structopt(name = "rm")]
+    Rm {
+        /// Path to file or directory.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Id of the comment, as shown by the comment list.
+        id: String,
+    },
+}
+
+/// User status subcommands
+#[derive(Debug, StructOpt)]
+enum UserStatusCommand {
+    /// Show your status.
+    #[structopt(name = "get")]
+    Get,
+    /// Set your status.
+    #[structopt(name = "set")]
+    Set {
+        /// Online status.
+        #[structopt(possible_values = &["online", "away", "dnd", "invisible"])]
+        status: String,
+
+        /// Status message, an empty message clears it.
+        #[structopt(short, long)]
+        message: Option<String>,
+
+        /// Clear the message after a duration, Ex: 30m, 1h, 1d.
+        #[structopt(long, requires = "message", parse(try_from_str = util::parse_duration))]
+        clear_after: Option<i64>,
+    },
+}
+
+/// Notification subcommands
+#[derive(Debug, StructOpt)]
+enum NotificationsCommand {
+    /// List your notifications, the default.
+    #[structopt(name = "list")]
+    List {
+        #[structopt(flatten)]
+        follow: FollowOpt,
+    },
+    /// Dismiss a notification.
+    #[structopt(name = "dismiss")]
+    Dismiss {
+        /// Id of the notification.
+        id: String,
+    },
+    /// Dismiss every notification.
+    #[structopt(name = "dismiss-all")]
+    DismissAll,
+}
+
+/// Options for following new notifications, accepted by notifications and its list subcommand
+#[derive(Debug, StructOpt)]
+struct FollowOpt {
+    /// Keep checking for new notifications and print them as they arrive, only when listing.
+    #[structopt(short, long)]
+    follow: bool,
+
+    /// Seconds between checks when following [default: 30].
+    #[structopt(long, parse(try_from_str = parse_interval))]
+    interval: Option<u64>,
+}
+
+/// Share subcommands
+#[derive(Debug, StructOpt)]
+enum ShareCommand {
+    /// Create a public link share and print its url.
+    #[structopt(name = "link")]
+    Link {
+        /// Path to file or directory to share.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Password to protect the link with.
+        #[structopt(short, long)]
+        password: Option<String>,
+
+        /// Date the link expires, Ex: 2021-12-31.
+        #[structopt(short, long, parse(try_from_str = parse_date))]
+        expire: Option<NaiveDate>,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+
+        /// Hide the download button for viewers of the link.
+        #[structopt(long)]
+        hide_download: bool,
+    },
+    /// Share with another user on the server.
+    #[structopt(name = "user")]
+    User {
+        /// Path to file or directory to share.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Id of the user to share with.
+        user: String,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+    },
+    /// Share with a group on the server.
+    #[structopt(name = "group")]
+    Group {
+        /// Path to file or directory to share.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Id of the group to share with.
+        group: String,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+    },
+    /// Share by sending a link to an email address.
+    #[structopt(name = "email")]
+    Email {
+        /// Path to file or directory to share.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Email address to share with.
+        email: String,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+    },
+    /// Share with a user on another NextCloud server.
+    #[structopt(name = "federated")]
+    Federated {
+        /// Path to file or directory to share.
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Federated cloud id to share with, Ex: user@other.cloud.
+        cloud_id: String,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+    },
+    /// Manage shares from other NextCloud servers waiting to be accepted.
+    #[structopt(name = "incoming")]
+    Incoming(IncomingCommand),
+    /// List your shares.
+    #[structopt(name = "list")]
+    List {
+        /// Only list the shares of this file or directory.
+        #[structopt(parse(from_os_str))]
+        path: Option<PathBuf>,
+    },
+    /// Update the attributes of a share.
+    #[structopt(name = "update")]
+    Update {
+        /// Id of the share.
+        id: String,
+
+        /// Password to protect the link with.
+        #[structopt(short, long)]
+        password: Option<String>,
+
+        /// Date the share expires, Ex: 2021-12-31.
+        #[structopt(short, long, parse(try_from_str = parse_date))]
+        expire: Option<NaiveDate>,
+
+        /// Comma separated permissions: read, update, create, delete, reshare or all.
+        #[structopt(long)]
+        permissions: Option<ocs::Permissions>,
+
+        /// Hide the download button for viewers of a link, true or false.
+        #[structopt(long)]
+        hide_download: Option<bool>,
+    },
+    /// Revoke a share.
+    #[structopt(name = "revoke")]
+    Revoke {
+        /// Id of the share.
+        id: String,
+    },
+}
+
+/// Incoming federated share subcommands
+#[derive(Debug, StructOpt)]
+enum IncomingCommand {
+    /// List pending shares.
+    #[structopt(name = "list")]
+    List,
+    /// Accept a pending share.
+    #[structopt(name = "accept")]
+    Accept {
+        /// Id of the pending share.
+        id: String,
+    },
+    /// Decline a pending share.
+    #[structopt(name = "decline")]
+    Decline {
+        /// Id of the pending share.
+        id: String,
+    },
+}
+
+/// Exit codes of the monitoring commands following the nagios plugin conventions
+const EXIT_WARNING: i32 = 1;
+const EXIT_CRITICAL: i32 = 2;
+const EXIT_UNKNOWN: i32 = 3;
+
+/// Error of the monitoring commands, ends the program with its exit code instead of 1
+#[derive(Debug)]
+struct MonitoringStatus {
+    code: i32,
+    message: String,
+}
+
+impl std::fmt::Display for MonitoringStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for MonitoringStatus {}
+
+/// Entrypoint of the program, returns 0 on success
+fn main() {
+    //Command::clap().gen_completions(env!("CARGO_PKG_NAME"), Shell::Bash, "target");
+    let current_dir = PathBuf::from("/");
+
+    let cli = Opt::from_args();
+
+    // Sets the log level
+    match cli.verbose {
+        0 => env_logger::builder()
+            .filter_level(log::LevelFilter::Warn)
+            .format_timestamp(None)
+            .init(),
+        1 => env_logger::builder()
+            .filter_level(log::LevelFilter::Info)
+            .format_timestamp(None)
+            .init(),
+        2 => env_logger::builder()
+            .filter_level(log::LevelFilter::Debug)
+            .format_timestamp(None)
+            .init(),
+        _ => env_logger::builder()
+            .filter_level(log::LevelFilter::Trace)
+            .format_timestamp(None)
+            .init(),
+    };
+
+    info!("Logger has been initialized");
+
+    if let Err(e) = run(cli, current_dir) {
+        if output::is_text() {
+            eprintln!("Error: {:?}", e);
+        } else {
+            output::error(&e);
+        }
+        let status = e.downcast_ref::<MonitoringStatus>();
+        std::process::exit(status.map_or(1, |s| s.code));
+    }
+}
+
+fn run(cli: Opt, mut current_dir: PathBuf) -> anyhow::Result<PathBuf> {
+    output::init(cli.output);
+    match cli.cmd {
+        Command::Status { remote } => status(remote)?,
+        Command::Whoami => status(true)?,
+        Command::Login { server, username, password, encrypt } => {
+            login(server, username, password, encrypt)?
+        }
+        Command::Logout { revoke } => logout(revoke)?,
+        Command::AppPassword(cmd) => app_password(cmd)?,
+        Command::Push { source, destination } => push(
+            source,
+            util::join_dedot_path(current_dir.clone(), destination)?,
+        )?,
+        Command::Pull { source, destination } => pull(
+            util::join_dedot_path(current_dir.clone(), source)?,
+            destination,
+        )?,
+        Command::PullPublic { link, destination, password, list } => {
+            pull_public(link, destination, password, list)?
+        }
+        Command::PushPublic { link, sources, password } => {
+            push_public(link, sources, password)?
+        }
+        Command::Ls { path, list, all } => {
+            let fp = match path {
+                Some(path) => util::join_dedot_path(current_dir.clone(), path)?,
+                None => current_dir.clone(),
+            };
+            ls(fp, list, all)?;
+        }
+        Command::Df { human_readable } => df(human_readable)?,
+        Command::Du { path, summarize, human_readable, max_depth } => {
+            let fp = match path {
+                Some(path) => util::join_dedot_path(current_dir.clone(), path)?,
+                None => current_dir.clone(),
+            };
+            let max_depth = if summarize { Some(0) } else { max_depth };
+            du(fp, human_readable, max_depth)?;
+        }
+        Command::Search {
+            path,
+            name,
+            mime,
+            min_size,
+            max_size,
+            newer,
+            older,
+            limit,
+            list,
+        } => {
+            let fp = match path {
+                Some(path) => util::join_dedot_path(current_dir.clone(), path)?,
+                None => current_dir.clone(),
+            };
+            let query = dav::SearchQuery {
+                name: name.map(|n| util::glob_to_like(&n, true)),
+                mime: mime.map(|m| util::glob_to_like(&m, false)),
+                min_size,
+                max_size,
+                modified_after: newer.map(date_timestamp),
+                modified_before: older.map(date_timestamp),
+                limit,
+            };
+            search(fp, query, list)?;
+        }
+        Command::Mkdir { path } => {
+            mkdir(util::join_dedot_path(current_dir.clone(), path)?)?
+        }
+        Command::Rm { path, force } => {
+            rm(util::join_dedot_path(current_dir.clone(), path)?, force)?
+        }
+        Command::Trash(cmd) => trash(cmd)?,
+        Command::Admin(cmd) => admin(cmd)?,
+        Command::Fav(cmd) => fav(cmd, &current_dir)?,
+        Command::Comment(cmd) => comment(cmd, &current_dir)?,
+        Command::ServerStatus { server } => monitoring(server_status(server))?,
+        Command::Ping { count, warning } => monitoring(ping(count, warning))?,
+        Command::Capabilities { refresh, all } => capabilities(refresh, all)?,
+        Command::Activity { path, activity_type, since, until, limit } => {
+            activity(
+                path.map(|p| util::join_dedot_path(current_dir.clone(), p))
+                    .transpose()?,
+                activity_type,
+                since,
+                until,
+                limit,
+            )?
+        }
+        Command::UserStatus(cmd) => user_status(cmd)?,
+        Command::Notifications { cmd, follow } => notifications(cmd, follow)?,
+        Command::Tag(cmd) => tag(cmd, &current_dir)?,
+        Command::Versions { path, cmd } => versions(path, cmd, &current_dir)?,
+        Command::Share(cmd) => share(cmd, &current_dir)?,
+        Command::Shell {} => shell(current_dir.clone())?,
+        Command::Cd { path } => {
+            current_dir = util::join_dedot_path(current_dir.clone(), path)?
+        }
+    };
+    Ok(current_dir)
+}
+
+/// Login to the nextcloud server
+fn login(
+    server: Url,
+    username: String,
+    password: String,
+    encrypt: bool,
+) -> anyhow::Result<()> {
+    let creds = Credentials::new(username, password, server);
+
+    let http = creds.clone().to_http();
+    http.verify_login()?;
+    if encrypt {
+        creds.write_encrypted(util::get_new_passphrase()?)?;
+    } else {
+        creds.write()?;
+    }
+    // never keep capabilities cached for a previous login
+    let _ = ocs::Capabilities::file_delete_default();
+    if let Err(e) = http
+        .get_capabilities()
+        .and_then(|c| c.file_write_default(http.server()))
+    {
+        warn!("Unable to cache server capabilities: {}", e);
+    }
+
+    output::print(&json!({ "logged_in": true }), || {
+        println!("Login successful")
+    })
+}
+
+/// Logout of the nextcloud server, with revoke the app password is revoked on the server first
+fn logout(revoke: bool) -> anyhow::Result<()> {
+    if revoke {
+        Credentials::read()?.to_http().revoke_app_password()?;
+    }
+
+    let _ = ocs::Capabilities::file_delete_default();
+    if Credentials::delete().is_err() {
+        return Err(anyhow!("Logout Failed"));
+    }
+
+    output::print(&json!({ "logged_in": false, "revoked": revoke }), || {
+        if revoke {
+            println!("App password revoked");
+        }
+        println!("Logout Successful")
+    })
+}
+
+/// Reports any other failure of a monitoring command as EXIT_UNKNOWN
+fn monitoring(result: anyhow::Result<()>) -> anyhow::Result<()> {
+    result.map_err(|e| {
+        if e.is::<MonitoringStatus>() {
+            e
+        } else {
+            MonitoringStatus { code: EXIT_UNKNOWN, message: format!("{:#}", e) }
+                .into()
+        }
+    })
+}
+
+/// Prints the status of the server, exits with a monitoring status code if it is not usable
+fn server_status(server: Option<Url>) -> anyhow::Result<()> {
+    let server = match server {
+        Some(server) => server,
+        None => Credentials::read()?.server,
+    };
+    let http = Credentials::new(String::new(), String::new(), server).to_http();
+
+    let status = http.get_server_status().map_err(|e| MonitoringStatus {
+        code: EXIT_CRITICAL,
+        message: format!("Unable to reach the server: {}", e),
+    })?;
+    output::print(&status, || {
+        println!(
+            "{} {} {}",
+            status.product_name, status.version_string, status.edition
+        );
+        println!("Installed: {}", status.installed);
+        println!("Maintenance: {}", status.maintenance);
+        println!("Needs database upgrade: {}", status.needs_db_upgrade);
+    })?;
+
+    if !status.installed || status.maintenance || status.needs_db_upgrade {
+        return Err(MonitoringStatus {
+            code: EXIT_WARNING,
+            message: String::from("Server is not usable"),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Times WebDAV requests for the root directory, exits with a monitoring status code if they fail or are slow
+fn ping(count: u32, warning: u128) -> anyhow::Result<()> {
+    if count == 0 {
+        return Err(anyhow!("Count must be at least 1"));
+    }
+    let creds = Credentials::read()?;
+    let server = creds.server.clone();
+    let http = creds.to_http();
+
+    let mut times = Vec::new();
+    for _ in 0..count {
+        let start = std::time::Instant::now();
+        if let Err(e) = http.get_entry(Path::new("/")) {
+            let maintenance =
+                e.downcast_ref::<http::MaintenanceMode>().is_some();
+            return Err(MonitoringStatus {
+                code: if maintenance { EXIT_WARNING } else { EXIT_CRITICAL },
+                message: e.to_string(),
+            }
+            .into());
+        }
+        let time = start.elapsed().as_millis();
+        if output::is_text() {
+            println!("Response from {}: time={} ms", server, time);
+        }
+        times.push(time);
+    }
+
+    if let (Some(min), Some(max)) = (times.iter().min(), times.iter().max()) {
+        let avg = times.iter().sum::<u128>() / times.len() as u128;
+        let result = json!({
+            "server": server.as_str(),
+            "times_ms": times,
+            "min_ms": min,
+            "avg_ms": avg,
+            "max_ms": max,
+        });
+        output::print(&result, || {
+            println!("min/avg/max = {}/{}/{} ms", min, avg, max)
+        })?;
+        if avg > warning {
+            return Err(MonitoringStatus {
+                code: EXIT_WARNING,
+                message: format!("Average latency is above {} ms", warning),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Prints the server version and which features it supports
+fn capabilities(refresh: bool, all: bool) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    if refresh {
+        http.get_capabilities()?.file_write_default(http.server())?;
+    }
+    let capabilities = server_capabilities(&http)?;
+    if all {
+        let pretty = serde_json::to_string_pretty(&capabilities.capabilities)?;
+        return output::print(&capabilities, || println!("{}", pretty));
+    }
+
+    let features: Vec<_> = ocs::Feature::ALL
+        .iter()
+        .map(|f| json!({ "feature": f.name(), "supported": capabilities.supports(*f) }))
+        .collect();
+    output::print(&features, || {
+        let version = &capabilities.version;
+        println!("NextCloud {} {}", version.string, version.edition);
+        for feature in ocs::Feature::ALL.iter() {
+            let supported = if capabilities.supports(*feature) {
+                "supported"
+            } else {
+                "not supported"
+            };
+            println!("{:<20}{}", feature.name(), supported);
+        }
+    })
+}
+
+/// Returns the cached capabilities of the server, fetching them if they are not cached yet
+fn server_capabilities(http: &http::Http) -> anyhow::Result<ocs::Capabilities> {
+    if let Ok(capabilities) =
+        ocs::Capabilities::file_read_default(http.server())
+    {
+        return Ok(capabilities);
+    }
+    let capabilities = http.get_capabilities()?;
+    if let Err(e) = capabilities.file_write_default(http.server()) {
+        warn!("Unable to cache server capabilities: {}", e);
+    }
+    Ok(capabilities)
+}
+
+/// Returns an Error if the server does not support the feature
+fn require(http: &http::Http, feature: ocs::Feature) -> anyhow::Result<()> {
+    server_capabilities(http)?.require(feature)
+}
+
+fn app_password(cmd: AppPasswordCommand) -> anyhow::Result<()> {
+    match cmd {
+        AppPasswordCommand::Create { server, username } => {
+            // stored credentials are only needed for what is not supplied, allowing this before the first login
+            let (server, username) = match (server, username) {
+                (Some(server), Some(username)) => (server, username),
+                (server, username) => {
+                    let creds = Credentials::read()?;
+                    (
+                        server.unwrap_or(creds.server),
+                        username.unwrap_or(creds.username),
+                    )
+                }
+            };
+            let password = rpassword::prompt_password(format!(
+                "Account password for {}: ",
+                username
+            ))?;
+            let http = Credentials::new(username, password, server).to_http();
+            let password = http.create_app_password()?;
+            output::print(&json!({ "app_password": password }), || {
+                println!("{}", password)
+            })?;
+        }
+        AppPasswordCommand::Revoke { force } => {
+            let warning = "Are you sure you want to revoke the app password you are logged in with, (y/n)";
+            if force || util::get_confirmation(warning)? {
+                logout(true)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the username and server of logged in user, with remote the user info is fetched from the server
+fn status(remote: bool) -> anyhow::Result<()> {
+    let creds = match Credentials::read() {
+        Ok(creds) => creds,
+        Err(_) => {
+            return output::print(&json!({ "logged_in": false }), || {
+                println!("Not logged in")
+            });
+        }
+    };
+    let server = creds.server.clone();
+    let username = creds.username.clone();
+    let user = if remote { Some(creds.to_http().get_user()?) } else { None };
+
+    let result = json!({
+        "logged_in": true,
+        "server": server.as_str(),
+        "username": username,
+        "user": user,
+    });
+    output::print(&result, || {
+        println!("Logged in to Server: '{}' as User: '{}'", server, username);
+        if let Some(user) = user {
+            print_user(user);
+        }
+    })
+}
+
+fn print_user(user: ocs::User) {
+    if let Some(display_name) = user.displayname {
+        println!("Display name: {}", display_name);
+    }
+    if let Some(email) = user.email.filter(|e| !e.is_empty()) {
+        println!("Email: {}", email);
+    }
+    println!("Groups: {}", user.groups.join(", "));
+    if let Some(quota) = user.quota {
+        let used = util::format_size(quota.used.unwrap_or(0));
+        match quota.total {
+            Some(total) if total > 0 => println!(
+                "Quota: {} used of {} ({:.2}%)",
+                used,
+                util::format_size(total),
+                quota.relative.unwrap_or(0.0)
+            ),
+            _ => println!("Quota: {} used", used),
+        }
+    }
+    match user.last_login {
+        Some(ms) if ms > 0 => {
+            println!("Last login: {}", util::format_timestamp(ms / 1000))
+        }
+        _ => println!("Last login: never"),
+    }
+}
+
+/// lists files
+fn ls(path: PathBuf, list: bool, all: bool) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    let entries = http.get_list(&path)?;
+
+    // the first entry is the directory itself
+    let entries: Vec<&dav::Entry> = entries
+        .iter()
+        .skip(1)
+        .filter(|e| all || !e.name().starts_with('.') || e.name().contains(' '))
+        .collect();
+
+    output::print(&entries, || {
+        let mut files: Vec<String> = vec![];
+        for entry in &entries {
+            let mut name = entry.name();
+            if entry.is_dir {
+                name.push('/');
+            }
+            if name.contains(' ') {
+                files.push(format!("'{}'", name))
+            } else {
+                files.push(name);
+            }
+        }
+        let print: String =
+            if list { files.join("\n") } else { files.join("  ") };
+        println!("{}", print);
+    })
+}
+
+/// Prints the storage usage of the account and of each top level folder
+fn df(human: bool) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    let entries = http.get_list(&PathBuf::from("/"))?;
+    let user = http.get_user()?;
+
+    let size = |bytes: i64| {
+        if human {
+            util::format_size(bytes)
+        } else {
+            bytes.to_string()
+        }
+    };
+
+    let root = entries.first().ok_or_else(|| anyhow!("No root directory"))?;
+    let used = root.quota_used.unwrap_or(0);
+    // negative values mean the quota is unlimited or unknown
+    let available = root.quota_available.filter(|a| *a >= 0);
+    let total = user
+        .quota
+        .and_then(|q| q.total)
+        .filter(|t| *t > 0 && root.quota_available != Some(-3));
+
+    let mut folders: Vec<_> =
+        entries.iter().skip(1).filter(|e| e.is_dir).collect();
+    folders.sort_by_key(|e| std::cmp::Reverse(e.quota_used.unwrap_or(0)));
+
+    let result = json!({
+        "used": used,
+        "available": available,
+        "total": total,
+        "folders": folders
+            .iter()
+            .map(|f| json!({ "name": f.name(), "used": f.quota_used.unwrap_or(0) }))
+            .collect::<Vec<_>>(),
+    });
+    output::print(&result, || {
+        let unlimited = || String::from("unlimited");
+        println!(
+            "Used: {}  Available: {}  Total: {}",
+            size(used),
+            available.map(size).unwrap_or_else(unlimited),
+            total.map(size).unwrap_or_else(unlimited)
+        );
+        println!();
+
+        for folder in folders {
+            println!(
+                "{:>12}  {}",
+                size(folder.quota_used.unwrap_or(0)),
+                folder.name()
+            );
+        }
+    })
+}
+
+/// Prints the size of the directory and its subdirectories up to the max depth
+fn du(
+    path: PathBuf,
+    human: bool,
+    max_depth: Option<usize>,
+) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    let entry = http.get_entry(&path)?;
+    let mut sizes = vec![];
+    du_walk(&http, &entry, 0, max_depth, &mut sizes)?;
+
+    output::print(&sizes, || {
+        for usage in &sizes {
+            let size = if human {
+                util::format_size(usage.size)
+            } else {
+                usage.size.to_string()
+            };
+            println!("{}\t{}", size, usage.path.to_string_lossy());
+        }
+    })
+}
+
+/// Size of a directory as reported by du
+#[derive(Debug, Serialize)]
+struct DiskUsage {
+    path: PathBuf,
+    size: i64,
+}
+
+/// Collects the sizes of the directory and its subdirectories, subdirectories first
+fn du_walk(
+    http: &http::Http,
+    entry: &dav::Entry,
+    depth: usize,
+    max_depth: Option<usize>,
+    sizes: &mut Vec<DiskUsage>,
+) -> anyhow::Result<()> {
+    if entry.is_dir && max_depth.is_none_or(|max| depth < max) {
+        let entries = http.get_list(&entry.path)?;
+        for child in entries.iter().skip(1).filter(|e| e.is_dir) {
+            du_walk(http, child, depth + 1, max_depth, sizes)?;
+        }
+    }
+
+    sizes.push(DiskUsage {
+        path: entry.path.clone(),
+        size: entry.size.unwrap_or(0),
+    });
+    Ok(())
+}
+
+/// Prints the files and directories matching the query
+fn search(
+    path: PathBuf,
+    query: dav::SearchQuery,
+    list: bool,
+) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    require(&http, ocs::Feature::Search)?;
+
+    let entries = http.search(&path, &query)?;
+    if !list {
+        return print_paths(&entries);
+    }
+
+    output::print(&entries, || {
+        for entry in &entries {
+            let mut name = entry.path.to_string_lossy().to_string();
+            if entry.is_dir {
+                name.push('/');
+            }
+            let modified = entry
+                .last_modified
+                .map(util::format_timestamp)
+                .unwrap_or_default();
+            let size = util::format_size(entry.size.unwrap_or(0));
+            println!("{:>10}  {}  {}", size, modified, name);
+        }
+    })
+}
+
+fn mkdir(path: PathBuf) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    creds.to_http().make_folder(&path)?;
+    output::print(&json!({ "created": path }), || {})
+}
+
+fn rm(path: PathBuf, force: bool) -> anyhow::Result<()> {
+    if path.to_string_lossy() == "/" {
+        error!("Deleting the root is not supported");
+        return Ok(());
+    }
+
+    let warning = format!(
+        "Are you sure you want to delete '{}', (y/n)",
+        path.to_string_lossy()
+    );
+
+    if !force {
+        warn!("DIRECTORIES DELETE ALL FILES AND DIRECTORIES RECURSIVELY");
+        if !util::get_confirmation(&warning)? {
+            return Ok(());
+        }
+    }
+
+    let creds = Credentials::read()?;
+
+    let http = creds.to_http();
+    http.delete(&path)?;
+    output::print(&json!({ "deleted": path }), || {})
+}
+
+/// Pulls a file from the server to your computer
+fn pull(source: PathBuf, destination: PathBuf) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    let new_dest = util::format_destination_pull(&source, &destination)?;
+    let new_src = util::format_source_pull(&source)?;
+
+    let data: Bytes = http.get_file(&new_src)?;
+    file::create_file(&new_dest, &data)?;
+
+    let transfer = Transfer::new(new_src, new_dest, data.len());
+    output::print(&transfer, || {
+        println!("Pulled {:?}, {:?}", transfer.source, transfer.destination)
+    })
+}
+
+/// Pulls a file or directory from a public share link
+fn pull_public(
+    link: Url,
+    destination: Option<PathBuf>,
+    password: Option<String>,
+    list: bool,
+) -> anyhow::Result<()> {
+    let http = http::Http::from_public_link(&link, password)?;
+    let root = http.get_entry(Path::new(""))?;
+
+    if list {
+        let mut entries = vec![];
+        list_public(&http, root, &mut entries)?;
+        return output::print(&entries, || {
+            for entry in &entries {
+                let size = util::format_size(entry.size.unwrap_or(0));
+                println!("{:>12}  {}", size, entry.path.to_string_lossy());
+            }
+        });
+    }
+
+    let name = match root.display_name.clone() {
+        Some(name) if !name.is_empty() => name,
+        _ => util::parse_public_link(&link)?.1,
+    };
+    let destination = destination.unwrap_or_else(|| PathBuf::from("."));
+
+    let mut transfers = vec![];
+    if root.is_dir {
+        let new_dest = if util::path_is_file(&destination) {
+            destination
+        } else {
+            destination.join(name)
+        };
+        pull_public_dir(&http, &root.path, &new_dest, &mut transfers)?;
+    } else {
+        let new_dest =
+            util::format_destination_pull(Path::new(&name), &destination)?;
+        let data: Bytes = http.get_file(&root.path)?;
+        file::create_file(&new_dest, &data)?;
+        transfers.push(Transfer::new(
+            PathBuf::from(name),
+            new_dest,
+            data.len(),
+        ));
+    }
+    print_transfers(&transfers, "Pulled")
+}
+
+/// Collects every file in a public share recursively
+fn list_public(
+    http: &http::Http,
+    entry: dav::Entry,
+    entries: &mut Vec<dav::Entry>,
+) -> anyhow::Result<()> {
+    if !entry.is_dir {
+        entries.push(entry);
+        return Ok(());
+    }
+    for child in http.get_list(&entry.path)?.into_iter().skip(1) {
+        list_public(http, child, entries)?;
+    }
+    Ok(())
+}
+
+/// Pulls a directory of a public share recursively
+fn pull_public_dir(
+    http: &http::Http,
+    source: &Path,
+    destination: &Path,
+    transfers: &mut Vec<Transfer>,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(destination)?;
+    for entry in http.get_list(source)?.iter().skip(1) {
+        let new_dest = destination.join(entry.name());
+        if entry.is_dir {
+            pull_public_dir(http, &entry.path, &new_dest, transfers)?;
+        } else {
+            let data: Bytes = http.get_file(&entry.path)?;
+            file::create_file(&new_dest, &data)?;
+            transfers.push(Transfer::new(
+                entry.path.clone(),
+                new_dest,
+                data.len(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Pushes files into the root of a public share link
+fn push_public(
+    link: Url,
+    sources: Vec<PathBuf>,
+    password: Option<String>,
+) -> anyhow::Result<()> {
+    let http = http::Http::from_public_link(&link, password)?;
+
+    let mut transfers = vec![];
+    for source in sources {
+        let new_dest = util::format_destination_push(&source, Path::new("."))?;
+        let data: Bytes = file::read_file(&source)?;
+        let size = data.len();
+        http.send_file(&new_dest, data)?;
+        transfers.push(Transfer::new(source, new_dest, size));
+    }
+    print_transfers(&transfers, "Push")
+}
+
+/// A file that was pushed or pulled
+#[derive(Debug, Serialize)]
+struct Transfer {
+    source: PathBuf,
+    destination: PathBuf,
+    size: usize,
+}
+
+impl Transfer {
+    fn new(source: PathBuf, destination: PathBuf, size: usize) -> Self {
+        Self { source, destination, size }
+    }
+}
+
+fn print_transfers(transfers: &[Transfer], verb: &str) -> anyhow::Result<()> {
+    output::print(transfers, || {
+        for transfer in transfers {
+            println!(
+                "{} {:?}, {:?}",
+                verb, transfer.source, transfer.destination
+            );
+        }
+    })
+}
+
+/// Uploads of at least this size check the available quota first
+const LARGE_UPLOAD_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Pushes a file from your computer to the server
+fn push(source: PathBuf, destination: PathBuf) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    let data: Bytes = file::read_file(&source)?;
+    let new_dest = util::format_destination_push(&source, &destination)?;
+
+    if data.len() as u64 >= LARGE_UPLOAD_SIZE {
+        let parent = new_dest.parent().unwrap_or_else(|| Path::new(""));
+        let available = http.get_entry(parent)?.quota_available;
+        if let Some(available) = available.filter(|a| *a >= 0) {
+            if data.len() as i64 > available {
+                return Err(anyhow!(
+                    "Not enough quota, {} is required but only {} is available",
+                    util::format_size(data.len() as i64),
+                    util::format_size(available)
+                ));
+            }
+        }
+    }
+
+    let size = data.len();
+    http.send_file(&new_dest, data)?;
+
+    let transfer = Transfer::new(source, new_dest, size);
+    output::print(&transfer, || {
+        println!("Push {:?}, {:?}", transfer.source, transfer.destination)
+    })
+}
+
+fn trash(cmd: TrashCommand) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    require(&http, ocs::Feature::Trash)?;
+
+    match cmd {
+        TrashCommand::List => {
+            let trash = http.get_trash()?;
+            output::print(&trash, || {
+                for item in &trash {
+                    let deleted = item
+                        .deletion_time
+                        .map(util::format_timestamp)
+                        .unwrap_or_default();
+                    println!(
+                        "{}  {:>10}  {}  ({})",
+                        deleted,
+                        util::format_size(item.size.unwrap_or(0)),
+                        item.original_location.as_deref().unwrap_or_default(),
+                        item.name()
+                    );
+                }
+            })?;
+        }
+        TrashCommand::Restore { items } => {
+            let trash = http.get_trash()?;
+            let mut restored = vec![];
+            for item in items {
+                let name = find_trash_item(&trash, &item)?;
+                http.restore_trash(&name)?;
+                restored.push(item);
+            }
+            output::print(&json!({ "restored": restored }), || {
+                for item in &restored {
+                    println!("Restored '{}'", item);
+                }
+            })?;
+        }
+        TrashCommand::Purge { items, force } => {
+            let trash = http.get_trash()?;
+            let mut purged = vec![];
+            for item in items {
+                let name = find_trash_item(&trash, &item)?;
+                let warning = format!(
+                    "Are you sure you want to permanently delete '{}', (y/n)",
+                    item
+                );
+                if force || util::get_confirmation(&warning)? {
+                    http.delete_trash(Some(&name))?;
+                    purged.push(item);
+                }
+            }
+            output::print(&json!({ "purged": purged }), || {})?;
+        }
+        TrashCommand::Empty { force } => {
+            let warning = "Are you sure you want to permanently delete every item in the trash bin, (y/n)";
+            let emptied = force || util::get_confirmation(warning)?;
+            if emptied {
+                http.delete_trash(None)?;
+            }
+            output::print(&json!({ "emptied": emptied }), || {})?;
+        }
+    }
+    Ok(())
+}
+
+/// Finds the trash item name of an item, matched by name or original location
+/// When multiple items were deleted from the same location the most recent one is used
+fn find_trash_item(trash: &[dav::Entry], item: &str) -> anyhow::Result<String> {
+    let location = item.trim_start_matches('/');
+    trash
+        .iter()
+        .filter(|e| {
+            e.name() == item || e.original_location.as_deref() == Some(location)
+        })
+        .max_by_key(|e| e.deletion_time)
+        .map(|e| e.name())
+        .ok_or_else(|| anyhow!("'{}' is not in the trash bin", item))
+}
+
+fn versions(
+    path: Option<PathBuf>,
+    cmd: Option<VersionsCommand>,
+    current_dir: &Path,
+) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    require(&http, ocs::Feature::Versions)?;
+
+    match (path, cmd) {
+        (Some(path), None) | (None, Some(VersionsCommand::List { path })) => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let file_id = get_file_id(&http, &path)?;
+            let versions = http.get_versions(&file_id)?;
+            output::print(&versions, || {
+                for version in &versions {
+                    let modified = version
+                        .last_modified
+                        .map(util::format_timestamp)
+                        .unwrap_or_default();
+                    println!(
+                        "{}  {}  {:>10}",
+                        version.name(),
+                        modified,
+                        util::format_size(version.size.unwrap_or(0))
+                    );
+                }
+            })?;
+        }
+        (None, Some(VersionsCommand::Pull { path, version, destination })) => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let file_id = get_file_id(&http, &path)?;
+            let new_dest = util::format_destination_pull(&path, &destination)?;
+
+            let data: Bytes = http.get_version(&file_id, &version)?;
+            file::create_file(&new_dest, &data)?;
+            let transfer = Transfer::new(path, new_dest, data.len());
+            output::print(&transfer, || {
+                println!(
+                    "Pulled {:?} version {}, {:?}",
+                    transfer.source, version, transfer.destination
+                )
+            })?;
+        }
+        (None, Some(VersionsCommand::Restore { path, version })) => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let file_id = get_file_id(&http, &path)?;
+            http.restore_version(&file_id, &version)?;
+            let result = json!({ "restored": path, "version": version });
+            output::print(&result, || {
+                println!("Restored {:?} to version {}", path, version)
+            })?;
+        }
+        _ => return Err(anyhow!("Supply either a path or a subcommand")),
+    }
+    Ok(())
+}
+
+/// Resolves the oc:fileid of a file, required by the endpoints that address files by id
+fn get_file_id(http: &http::Http, path: &Path) -> anyhow::Result<String> {
+    let entry = http.get_entry(path)?;
+    if entry.is_dir {
+        return Err(anyhow!("{:?} is a directory", path));
+    }
+    entry.file_id.ok_or_else(|| {
+        anyhow!("Server did not return a file id for {:?}", path)
+    })
+}
+
+/// Resolves the oc:fileid of a file or directory
+fn get_entry_id(http: &http::Http, path: &Path) -> anyhow::Result<String> {
+    http.get_entry(path)?.file_id.ok_or_else(|| {
+        anyhow!("Server did not return a file id for {:?}", path)
+    })
+}
+
+fn fav(cmd: FavCommand, current_dir: &Path) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    match cmd {
+        FavCommand::Add { path } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            http.set_favorite(&path, true)?;
+            output::print(&json!({ "path": path, "favorite": true }), || {})
+        }
+        FavCommand::Rm { path } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            http.set_favorite(&path, false)?;
+            output::print(&json!({ "path": path, "favorite": false }), || {})
+        }
+        FavCommand::Ls => print_paths(&http.get_favorites()?),
+    }
+}
+
+fn tag(cmd: TagCommand, current_dir: &Path) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    match cmd {
+        TagCommand::Add { path, tag } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let file_id = get_entry_id(&http, &path)?;
+            let tag_id = match lookup_tag(&http, &tag)? {
+                Some(id) => id,
+                None => http.create_tag(&tag)?,
+            };
+            http.tag_file(&file_id, &tag_id)?;
+            output::print(&json!({ "path": path, "tagged": tag }), || {})
+        }
+        TagCommand::Rm { path, tag } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let file_id = get_entry_id(&http, &path)?;
+            http.untag_file(&file_id, &find_tag(&http, &tag)?)?;
+            output::print(&json!({ "path": path, "untagged": tag }), || {})
+        }
+        TagCommand::Ls { path } => {
+            let file_id = match path {
+                Some(path) => Some(get_entry_id(
+                    &http,
+                    &util::join_dedot_path(current_dir.to_path_buf(), path)?,
+                )?),
+                None => None,
+            };
+            let tags = http.get_tags(file_id.as_deref())?;
+            output::print(&tags, || {
+                for tag in &tags {
+                    println!("{}", tag.name);
+                }
+            })
+        }
+        TagCommand::Find { tag } => {
+            print_paths(&http.get_tagged_files(&find_tag(&http, &tag)?)?)
+        }
+    }
+}
+
+fn comment(cmd: CommentCommand, current_dir: &Path) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    match cmd {
+        CommentCommand::Add { path, message } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let id =
+                http.add_comment(&get_entry_id(&http, &path)?, &message)?;
+            output::print(&json!({ "id": id }), || {
+                println!("Added comment {}", id)
+            })
+        }
+        CommentCommand::Ls { path, limit } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let comments =
+                http.get_comments(&get_entry_id(&http, &path)?, limit)?;
+            output::print(&comments, || {
+                for comment in &comments {
+                    let created = comment
+                        .created
+                        .map(util::format_timestamp)
+                        .unwrap_or_default();
+                    let author = comment
+                        .actor_display_name
+                        .as_ref()
+                        .unwrap_or(&comment.actor_id);
+                    println!(
+                        "{:>6}  {}  {}: {}",
+                        comment.id, created, author, comment.message
+                    );
+                }
+            })
+        }
+        CommentCommand::Rm { path, id } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            http.delete_comment(&get_entry_id(&http, &path)?, &id)?;
+            output::print(&json!({ "deleted": id }), || {})
+        }
+    }
+}
+
+fn activity(
+    path: Option<PathBuf>,
+    activity_type: Option<String>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    limit: usize,
+) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    let file_id = path.map(|p| get_entry_id(&http, &p)).transpose()?;
+    let since = since.map(date_timestamp);
+    let until = until.map(date_timestamp);
+
+    let mut matches = vec![];
+    let mut last = None;
+    // Activities come newest first, page until enough match or they get too old
+    'pages: loop {
+        let activities =
+            http.get_activities(file_id.as_deref(), last, limit)?;
+        if activities.is_empty() {
+            break;
+        }
+        last = activities.last().map(|a| a.activity_id);
+
+        for activity in activities {
+            let time = chrono::DateTime::parse_from_rfc3339(&activity.datetime)
+                .map(|t| t.timestamp())
+                .unwrap_or_default();
+            if since.is_some_and(|since| time < since) {
+                break 'pages;
+            }
+            if until.is_some_and(|until| time >= until)
+                || activity_type
+                    .as_ref()
+                    .is_some_and(|t| *t != activity.activity_type)
+            {
+                continue;
+            }
+
+            matches.push(activity);
+            if matches.len() >= limit {
+                break 'pages;
+            }
+        }
+    }
+
+    output::print(&matches, || {
+        for activity in &matches {
+            let time = chrono::DateTime::parse_from_rfc3339(&activity.datetime)
+                .map(|t| util::format_timestamp(t.timestamp()))
+                .unwrap_or_else(|_| activity.datetime.clone());
+            println!(
+                "{}  {:<14} {}",
+                time, activity.activity_type, activity.subject
+            );
+        }
+    })
+}
+
+fn user_status(cmd: UserStatusCommand) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    let status = match cmd {
+        UserStatusCommand::Get => http.get_user_status()?,
+        UserStatusCommand::Set { status, message, clear_after } => {
+            let mut user_status = http.set_user_status(&status)?;
+            match message {
+                Some(message) if message.is_empty() => {
+                    http.clear_user_status_message()?;
+                    user_status.message = None;
+                }
+                Some(message) => {
+                    let clear_at = clear_after
+                        .map(|secs| chrono::Local::now().timestamp() + secs);
+                    user_status =
+                        http.set_user_status_message(&message, clear_at)?;
+                }
+                None => {}
+            }
+            user_status
+        }
+    };
+
+    output::print(&status, || {
+        println!("Status: {}", status.status);
+        if let Some(message) = status.message.as_ref().filter(|m| !m.is_empty())
+        {
+            let icon = status.icon.as_ref().map(|i| format!("{} ", i));
+            println!("Message: {}{}", icon.unwrap_or_default(), message);
+        }
+        if let Some(clear_at) = status.clear_at {
+            println!("Clears at: {}", util::format_timestamp(clear_at));
+        }
+    })
+}
+
+fn notifications(
+    cmd: Option<NotificationsCommand>,
+    opt: FollowOpt,
+) -> anyhow::Result<()> {
+    let (follow, interval) = match &cmd {
+        None => (opt.follow, opt.interval),
+        Some(NotificationsCommand::List { follow }) => {
+            (opt.follow || follow.follow, follow.interval.or(opt.interval))
+        }
+        // clap can not declare a conflict between an argument and a subcommand
+        Some(_) if opt.follow || opt.interval.is_some() => {
+            return Err(anyhow!(
+                "--follow and --interval can only be used to list notifications"
+            ));
+        }
+        Some(_) => (false, None),
+    };
+    let interval = interval.unwrap_or(30);
+
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    match cmd {
+        None | Some(NotificationsCommand::List { .. }) => {
+            let mut seen = std::collections::HashSet::new();
+            loop {
+                let mut notifications = http.get_notifications()?;
+                notifications.sort_by_key(|n| n.notification_id);
+                notifications.retain(|n| seen.insert(n.notification_id));
+                // when following every check prints its new notifications
+                if !follow || !notifications.is_empty() {
+                    output::print(&notifications, || {
+                        notifications.iter().for_each(print_notification)
+                    })?;
+                }
+                if !follow {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(interval));
+            }
+        }
+        Some(NotificationsCommand::Dismiss { id }) => {
+            http.delete_notification(Some(&id))?;
+            output::print(&json!({ "dismissed": id }), || {})?;
+        }
+        Some(NotificationsCommand::DismissAll) => {
+            http.delete_notification(None)?;
+            output::print(&json!({ "dismissed": "all" }), || {})?;
+        }
+    }
+    Ok(())
+}
+
+fn print_notification(notification: &ocs::Notification) {
+    let time = chrono::DateTime::parse_from_rfc3339(&notification.datetime)
+        .map(|t| util::format_timestamp(t.timestamp()))
+        .unwrap_or_else(|_| notification.datetime.clone());
+    println!(
+        "{:>6}  {}  [{}] {}",
+        notification.notification_id,
+        time,
+        notification.app,
+        notification.subject
+    );
+    if !notification.message.is_empty() {
+        println!("        {}", notification.message);
+    }
+}
+
+fn admin(cmd: AdminCommand) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+
+    match cmd {
+        AdminCommand::User(cmd) => admin_user(&http, cmd),
+        AdminCommand::Group(cmd) => admin_group(&http, cmd),
+        AdminCommand::App(cmd) => admin_app(&http, cmd),
+    }
+}
+
+fn admin_app(http: &http::Http, cmd: AdminAppCommand) -> anyhow::Result<()> {
+    match cmd {
+        AdminAppCommand::List { enabled, disabled } => {
+            let filter = match (enabled, disabled) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            print_ids(&http.get_apps(filter)?)
+        }
+        AdminAppCommand::Enable { id } => {
+            http.set_app_enabled(&id, true)?;
+            output::print(&json!({ "app": id, "enabled": true }), || {})
+        }
+        AdminAppCommand::Disable { id } => {
+            http.set_app_enabled(&id, false)?;
+            output::print(&json!({ "app": id, "enabled": false }), || {})
+        }
+    }
+}
+
+fn admin_group(
+    http: &http::Http,
+    cmd: AdminGroupCommand,
+) -> anyhow::Result<()> {
+    match cmd {
+        AdminGroupCommand::List { search, limit } => {
+            print_ids(&http.get_groups(search.as_deref(), limit)?)
+        }
+        AdminGroupCommand::Create { id } => {
+            http.create_group(&id)?;
+            output::print(&json!({ "created": id }), || {
+                println!("Created group {}", id)
+            })
+        }
+        AdminGroupCommand::Delete { id } => {
+            http.delete_group(&id)?;
+            output::print(&json!({ "deleted": id }), || {})
+        }
+        AdminGroupCommand::Members { id } => {
+            print_ids(&http.get_group_members(&id)?)
+        }
+        AdminGroupCommand::Subadmins { id } => {
+            print_ids(&http.get_group_subadmins(&id)?)
+        }
+    }
+}
+
+fn admin_user(http: &http::Http, cmd: AdminUserCommand) -> anyhow::Result<()> {
+    match cmd {
+        AdminUserCommand::List { search, limit, long } => {
+            let ids = http.get_users(search.as_deref(), limit)?;
+            if !long {
+                return print_ids(&ids);
+            }
+
+            let users = ids
+                .iter()
+                .map(|id| http.get_user_by_id(id))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            output::print(&users, || {
+                for user in &users {
+                    let quota =
+                        user.quota.as_ref().map_or_else(String::new, |q| {
+                            format!(
+                                "{} of {}",
+                                util::format_size(q.used.unwrap_or(0)),
+                                match q.total {
+                                    Some(total) if total > 0 =>
+                                        util::format_size(total),
+                                    _ => String::from("unlimited"),
+                                }
+                            )
+                        });
+                    println!(
+                        "{:<20} {:<8} {:<24} {:<32} {:<24} {}",
+                        user.id,
+                        if user.enabled == Some(false) {
+                            "disabled"
+                        } else {
+                            "enabled"
+                        },
+                        user.displayname.as_deref().unwrap_or_default(),
+                        user.email.as_deref().unwrap_or_default(),
+                        quota,
+                        user.groups.join(",")
+                    );
+                }
+            })
+        }
+        AdminUserCommand::Create {
+            id,
+            password,
+            display_name,
+            email,
+            groups,
+            quota,
+        } => {
+            let mut params = vec![("userid", id.clone())];
+            if let Some(password) = password {
+                params.push(("password", password));
+            }
+            if let Some(display_name) = display_name {
+                params.push(("displayName", display_name));
+            }
+            if let Some(email) = email {
+                params.push(("email", email));
+            }
+            if let Some(quota) = quota {
+                params.push(("quota", quota));
+            }
+            params.extend(groups.into_iter().map(|g| ("groups[]", g)));
+            http.create_user(&params)?;
+            output::print(&json!({ "created": id }), || {
+                println!("Created user {}", id)
+            })
+        }
+        AdminUserCommand::Disable { id } => {
+            http.set_user_enabled(&id, false)?;
+            output::print(&json!({ "user": id, "enabled": false }), || {})
+        }
+        AdminUserCommand::Enable { id } => {
+            http.set_user_enabled(&id, true)?;
+            output::print(&json!({ "user": id, "enabled": true }), || {})
+        }
+        AdminUserCommand::Delete { id, force } => {
+            let warning = format!(
+                "Are you sure you want to delete user '{}' and all their files, (y/n)",
+                id
+            );
+            let deleted = force || util::get_confirmation(&warning)?;
+            if deleted {
+                http.delete_user(&id)?;
+            }
+            output::print(&json!({ "user": id, "deleted": deleted }), || {})
+        }
+        AdminUserCommand::Quota { id, quota } => {
+            http.edit_user(&id, "quota", &quota)?;
+            output::print(&json!({ "user": id, "quota": quota }), || {})
+        }
+        AdminUserCommand::AddGroup { id, group } => {
+            http.set_user_group(&id, &group, true)?;
+            output::print(&json!({ "user": id, "added_to": group }), || {})
+        }
+        AdminUserCommand::RemoveGroup { id, group } => {
+            http.set_user_group(&id, &group, false)?;
+            output::print(&json!({ "user": id, "removed_from": group }), || {})
+        }
+        AdminUserCommand::ResendWelcome { id } => {
+            http.resend_welcome(&id)?;
+            output::print(&json!({ "user": id, "welcome_sent": true }), || {})
+        }
+    }
+}
+
+/// Prints user, group or app ids one per line
+fn print_ids(ids: &[String]) -> anyhow::Result<()> {
+    output::print(ids, || ids.iter().for_each(|id| println!("{}", id)))
+}
+
+/// Finds the id of a system tag by name, returns None if there is no such tag
+fn lookup_tag(http: &http::Http, name: &str) -> anyhow::Result<Option<String>> {
+    Ok(http.get_tags(None)?.into_iter().find(|t| t.name == name).map(|t| t.id))
+}
+
+/// Finds the id of a system tag by name, returns an Error if there is no such tag
+fn find_tag(http: &http::Http, name: &str) -> anyhow::Result<String> {
+    lookup_tag(http, name)?
+        .ok_or_else(|| anyhow!("Tag '{}' does not exist", name))
+}
+
+/// Prints the full paths of entries, directories end with '/'
+fn print_paths(entries: &[dav::Entry]) -> anyhow::Result<()> {
+    output::print(entries, || {
+        for entry in entries {
+            let mut name = entry.path.to_string_lossy().to_string();
+            if entry.is_dir {
+                name.push('/');
+            }
+            println!("{}", name);
+        }
+    })
+}
+
+fn share(cmd: ShareCommand, current_dir: &Path) -> anyhow::Result<()> {
+    let creds = Credentials::read()?;
+    let http = creds.to_http();
+    require(&http, ocs::Feature::Sharing)?;
+
+    match cmd {
+        ShareCommand::Link {
+            path,
+            password,
+            expire,
+            permissions,
+            hide_download,
+        } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let mut params = share_params(&path, ocs::ShareType::Link, None);
+            if let Some(password) = password {
+                params.push(("password", password));
+            }
+            if let Some(expire) = expire {
+                params.push(("expireDate", expire.to_string()));
+            }
+            if let Some(permissions) = permissions {
+                params.push(("permissions", permissions.0.to_string()));
+            }
+
+            let mut share = http.create_share(&params)?;
+            if hide_download {
+                let params = [("hideDownload", String::from("true"))];
+                share = http.update_share(&share.id, &params)?;
+            }
+
+            let url = share
+                .url
+                .clone()
+                .ok_or_else(|| anyhow!("Server did not return a link"))?;
+            output::print(&share, || println!("{}", url))?;
+        }
+        ShareCommand::User { path, user, permissions } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            share_with(&http, &path, ocs::ShareType::User, user, permissions)?
+        }
+        ShareCommand::Group { path, group, permissions } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            share_with(&http, &path, ocs::ShareType::Group, group, permissions)?
+        }
+        ShareCommand::Email { path, email, permissions } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            share_with(&http, &path, ocs::ShareType::Email, email, permissions)?
+        }
+        ShareCommand::Federated { path, cloud_id, permissions } => {
+            let path = util::join_dedot_path(current_dir.to_path_buf(), path)?;
+            let share_type = ocs::ShareType::Federated;
+            share_with(&http, &path, share_type, cloud_id, permissions)?
+        }
+        ShareCommand::Incoming(IncomingCommand::List) => {
+            let shares = http.get_pending_remote_shares()?;
+            output::print(&shares, || {
+                for share in &shares {
+                    println!(
+                        "{:>6}  {}  from {} on {}",
+                        share.id, share.name, share.owner, share.remote
+                    );
+                }
+            })?;
+        }
+        ShareCommand::Incoming(IncomingCommand::Accept { id }) => {
+            http.accept_remote_share(&id)?;
+            output::print(&json!({ "accepted": id }), || {
+                println!("Accepted share {}", id)
+            })?;
+        }
+        ShareCommand::Incoming(IncomingCommand::Decline { id }) => {
+            http.decline_remote_share(&id)?;
+            output::print(&json!({ "declined": id }), || {
+                println!("Declined share {}", id)
+            })?;
+        }
+        ShareCommand::List { path } => {
+            let path = match path {
+                Some(path) => Some(util::join_dedot_path(
+                    current_dir.to_path_buf(),
+                    path,
+                )?),
+                None => None,
+            };
+            let shares = http.get_shares(path.as_deref())?;
+            output::print(&shares, || {
+                for share in &shares {
+                    let with = share
+                        .url
+                        .as_ref()
+                        .or(share.share_with_displayname.as_ref())
+                        .or(share.share_with.as_ref())
+                        .cloned()
+                        .unwrap_or_default();
+                    let expiration = share
+                        .expiration
+                        .as_ref()
+                        .map(|e| format!(" expires {}", &e[..10.min(e.len())]))
+                        .unwrap_or_default();
+                    println!(
+                        "{:>6}  {:<6} {}  {}  [{}]{}",
+                        share.id,
+                        ocs::ShareType::name(share.share_type),
+                        share.path,
+                        with,
+                        ocs::Permissions(share.permissions),
+                        expiration
+                    );
+                }
+            })?;
+        }
+        ShareCommand::Update {
+            id,
+            password,
+            expire,
+            permissions,
+            hide_download,
+        } => {
+            // the api only supports updating a single attribute per request
+            let mut updates = vec![];
+            if let Some(password) = password {
+                updates.push(("password", password));
+            }
+            if let Some(expire) = expire {
+                updates.push(("expireDate", expire.to_string()));
+            }
+            if let Some(permissions) = permissions {
+                updates.push(("permissions", permissions.0.to_string()));
+            }
+            if let Some(hide_download) = hide_download {
+                updates.push(("hideDownload", hide_download.to_string()));
+            }
+            if updates.is_empty() {
+                return Err(anyhow!("Nothing to update"));
+            }
+
+            let mut share = None;
+            for update in updates {
+                share = Some(http.update_share(&id, &[update])?);
+            }
+            output::print(&share, || println!("Updated share {}", id))?;
+        }
+        ShareCommand::Revoke { id } => {
+            http.delete_share(&id)?;
+            output::print(&json!({ "revoked": id }), || {
+                println!("Revoked share {}", id)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Shares the path with a recipient, a user, group or email address depending on the type
+fn share_with(
+    http: &http::Http,
+    path: &Path,
+    share_type: ocs::ShareType,
+    with: String,
+    permissions: Option<ocs::Permissions>,
+) -> anyhow::Result<()> {
+    let mut params = share_params(path, share_type, Some(with));
+    if let Some(permissions) = permissions {
+        params.push(("permissions", permissions.0.to_string()));
+    }
+
+    let share = http.create_share(&params)?;
+    output::print(&share, || {
+        println!(
+            "Shared '{}' with {} '{}' (id {})",
+            share.path,
+            ocs::ShareType::name(share.share_type),
+            share.share_with.as_deref().unwrap_or_default(),
+            share.id
+        )
+    })
+}
+
+/// Form parameters common to creating every type of share
+fn share_params(
+    path: &Path,
+    share_type: ocs::ShareType,
+    share_with: Option<String>,
+) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("path", path.to_string_lossy().to_string()),
+        ("shareType", (share_type as u8).to_string()),
+    ];
+    if let Some(share_with) = share_with {
+        params.push(("shareWith", share_with));
+    }
+    params
 }
 
 fn shell(mut current_dir: PathBuf) -> anyhow::Result<()> {
@@ -430,6 +2730,34 @@ fn shell(mut current_dir: PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn parse_date(src: &str) -> Result<NaiveDate, chrono::ParseError> {
+    NaiveDate::parse_from_str(src, "%Y-%m-%d")
+}
+
+/// Unix timestamp of the start of the day in local time
+fn date_timestamp(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|d| d.and_local_timezone(chrono::Local).earliest())
+        .map_or(0, |d| d.timestamp())
+}
+
+/// Parses a quota as bytes, none and default are passed on as is
+fn parse_quota(src: &str) -> Result<String, String> {
+    match src {
+        "none" | "default" => Ok(src.to_string()),
+        _ => util::parse_size(src).map(|size| size.to_string()),
+    }
+}
+
+/// Parses the seconds between checks, at least 1 so the server is not polled in a loop
+fn parse_interval(src: &str) -> Result<u64, String> {
+    match src.parse::<u64>() {
+        Ok(0) => Err(String::from("Interval must be at least 1 second")),
+        Ok(interval) => Ok(interval),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn parse_url(src: &str) -> Result<Url, ParseError> {
     if src.contains("http") {
         Url::parse(src)
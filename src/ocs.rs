@@ -0,0 +1,607 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Envelope every OCS json response is wrapped in
+#[derive(Debug, Deserialize)]
+struct OcsResponse {
+    ocs: Ocs,
+}
+
+#[derive(Debug, Deserialize)]
+struct Ocs {
+    meta: Meta,
+    data: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Meta {
+    statuscode: u16,
+    message: Option<String>,
+}
+
+/// Parses an OCS json response, returns the data or an Error if the OCS status is not successful
+pub fn parse<T: DeserializeOwned>(body: &str) -> Result<T> {
+    let response: OcsResponse = serde_json::from_str(body)?;
+    let meta = response.ocs.meta;
+
+    // v1 endpoints use 100 for success, v2 endpoints use 200
+    if meta.statuscode != 100 && meta.statuscode != 200 {
+        return Err(anyhow!(
+            "Server responded with OCS status {}: {}",
+            meta.statuscode,
+            meta.message.unwrap_or_default()
+        ));
+    }
+
+    Ok(serde_json::from_value(response.ocs.data)?)
+}
+
+/// A NextCloud user as returned by the provisioning API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: String,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub displayname: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub quota: Option<Quota>,
+    /// Unix timestamp in milliseconds, 0 if the user never logged in
+    #[serde(default, rename = "lastLogin")]
+    pub last_login: Option<i64>,
+}
+
+/// User ids as returned by the user search of the provisioning API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserList {
+    pub users: Vec<String>,
+}
+
+/// Group ids as returned by the group search of the provisioning API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupList {
+    pub groups: Vec<String>,
+}
+
+/// App ids as returned by the app list of the provisioning API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppList {
+    pub apps: Vec<String>,
+}
+
+/// A new app password as returned by the getapppassword API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppPassword {
+    #[serde(rename = "apppassword")]
+    pub app_password: String,
+}
+
+/// Online status of a user as returned by the user status API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStatus {
+    /// online, away, dnd, invisible or offline
+    pub status: String,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Unix timestamp in seconds of when the message is cleared
+    #[serde(default)]
+    pub clear_at: Option<i64>,
+}
+
+/// Storage quota of a user, all sizes are in bytes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quota {
+    #[serde(default, deserialize_with = "bytes_from_number")]
+    pub free: Option<i64>,
+    #[serde(default, deserialize_with = "bytes_from_number")]
+    pub used: Option<i64>,
+    #[serde(default, deserialize_with = "bytes_from_number")]
+    pub total: Option<i64>,
+    #[serde(default)]
+    pub relative: Option<f64>,
+}
+
+/// Type of a share, values match the OCS Share API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareType {
+    User = 0,
+    Group = 1,
+    Link = 3,
+    Email = 4,
+    Federated = 6,
+}
+
+impl ShareType {
+    /// Returns a readable name for the share type value of a share
+    pub fn name(share_type: u8) -> &'static str {
+        match share_type {
+            0 => "user",
+            1 => "group",
+            3 => "link",
+            4 => "email",
+            6 => "federated",
+            _ => "other",
+        }
+    }
+}
+
+/// A share as returned by the OCS Share API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub share_type: u8,
+    pub uid_owner: String,
+    pub permissions: u8,
+    /// Unix timestamp in seconds of when the share was created
+    pub stime: i64,
+    /// Expiration date formatted as YYYY-MM-DD 00:00:00
+    #[serde(default)]
+    pub expiration: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+    pub path: String,
+    pub item_type: String,
+    #[serde(default)]
+    pub share_with: Option<String>,
+    #[serde(default)]
+    pub share_with_displayname: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub hide_download: bool,
+}
+
+/// A share from another NextCloud server as returned by the federated sharing API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteShare {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    /// Url of the server the share is from
+    pub remote: String,
+    pub name: String,
+    pub owner: String,
+    #[serde(default, deserialize_with = "bool_from_int")]
+    pub accepted: bool,
+}
+
+/// A notification as returned by the notifications API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub notification_id: i64,
+    pub app: String,
+    /// ISO 8601 date and time
+    pub datetime: String,
+    pub subject: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub link: String,
+}
+
+/// An entry of the activity stream as returned by the activity API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activity {
+    pub activity_id: i64,
+    pub app: String,
+    /// Kind of activity Ex: file_created, file_changed, shared
+    #[serde(rename = "type")]
+    pub activity_type: String,
+    /// User who caused the activity
+    #[serde(default)]
+    pub user: Option<String>,
+    pub subject: String,
+    #[serde(default)]
+    pub object_name: Option<String>,
+    /// ISO 8601 date and time
+    pub datetime: String,
+}
+
+/// Capabilities of a server as returned by the capabilities API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: Version,
+    /// Capabilities of every app, kept as is so they can be displayed
+    pub capabilities: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub micro: u32,
+    pub string: String,
+    #[serde(default)]
+    pub edition: String,
+}
+
+/// Status of a server as returned by status.php, which is not wrapped in an OCS envelope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub installed: bool,
+    pub maintenance: bool,
+    #[serde(default)]
+    pub needs_db_upgrade: bool,
+    pub version: String,
+    #[serde(rename = "versionstring")]
+    pub version_string: String,
+    #[serde(default)]
+    pub edition: String,
+    #[serde(default, rename = "productname")]
+    pub product_name: String,
+}
+
+/// Features a server may not provide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    ChunkingV2,
+    Versions,
+    Trash,
+    Sharing,
+    Search,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [
+        Feature::ChunkingV2,
+        Feature::Versions,
+        Feature::Trash,
+        Feature::Sharing,
+        Feature::Search,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::ChunkingV2 => "Chunked upload v2",
+            Feature::Versions => "Versions",
+            Feature::Trash => "Trash",
+            Feature::Sharing => "Sharing",
+            Feature::Search => "Search",
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn supports(&self, feature: Feature) -> bool {
+        let enabled = |pointer| {
+            self.capabilities.pointer(pointer) == Some(&Value::Bool(true))
+        };
+        match feature {
+            // the capability is the highest supported chunking version Ex: "1.0"
+            Feature::ChunkingV2 => self
+                .capabilities
+                .pointer("/dav/chunking")
+                .and_then(Value::as_str)
+                .and_then(|v| v.split('.').next())
+                .and_then(|major| major.parse::<u32>().ok())
+                .is_some_and(|major| major >= 2),
+            Feature::Versions => enabled("/files/versioning"),
+            Feature::Trash => enabled("/files/undelete"),
+            Feature::Sharing => enabled("/files_sharing/api_enabled"),
+            // WebDAV SEARCH has no capability, it is available since NextCloud 13
+            Feature::Search => self.version.major >= 13,
+        }
+    }
+
+    /// Returns an Error if the server does not support the feature
+    pub fn require(&self, feature: Feature) -> Result<()> {
+        if self.supports(feature) {
+            Ok(())
+        } else {
+            Err(anyhow!("{} is not supported by this server", feature.name()))
+        }
+    }
+}
+
+/// Share permissions bitmask, parsed from a comma separated list Ex: read,update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions(pub u8);
+
+impl Permissions {
+    const NAMES: [(&'static str, u8); 5] = [
+        ("read", 1),
+        ("update", 2),
+        ("create", 4),
+        ("delete", 8),
+        ("reshare", 16),
+    ];
+}
+
+impl FromStr for Permissions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bits = 0;
+        for name in s.split(',').map(|n| n.trim().to_lowercase()) {
+            match Self::NAMES.iter().find(|(n, _)| *n == name) {
+                Some((_, bit)) => bits |= bit,
+                None if name == "share" => bits |= 16,
+                None if name == "all" => bits |= 31,
+                None => {
+                    return Err(format!(
+                        "Unknown permission '{}', expected read, update, create, delete, reshare or all",
+                        name
+                    ))
+                }
+            }
+        }
+        Ok(Self(bits))
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = Self::NAMES
+            .iter()
+            .filter(|(_, bit)| self.0 & bit != 0)
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+/// Some OCS fields are strings or numbers depending on the server version
+fn string_or_number<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<String, D::Error> {
+    match Value::deserialize(d)? {
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        other => Err(de::Error::custom(format!("unexpected value {}", other))),
+    }
+}
+
+/// Older servers send quota sizes as floats for users with unlimited quota
+fn bytes_from_number<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<i64>, D::Error> {
+    match Value::deserialize(d)? {
+        Value::Number(n) => {
+            Ok(n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)))
+        }
+        Value::Null => Ok(None),
+        other => Err(de::Error::custom(format!("unexpected value {}", other))),
+    }
+}
+
+fn bool_from_int<'de, D: Deserializer<'de>>(d: D) -> Result<bool, D::Error> {
+    match Value::deserialize(d)? {
+        Value::Bool(b) => Ok(b),
+        Value::Number(n) => Ok(n.as_i64() != Some(0)),
+        Value::Null => Ok(false),
+        other => Err(de::Error::custom(format!("unexpected value {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_user() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"enabled":true,"id":"test","lastLogin":1612345678000,
+            "quota":{"free":900,"used":100,"total":1000,"relative":10.0,"quota":1000},
+            "email":"test@example.com","displayname":"Test User","groups":["admin","users"]}}}"#;
+        let user: User = parse(body).unwrap();
+        assert_eq!(user.id, "test");
+        assert_eq!(user.displayname.as_deref(), Some("Test User"));
+        assert_eq!(user.groups, vec!["admin", "users"]);
+        assert_eq!(user.quota.unwrap().used, Some(100));
+        assert_eq!(user.last_login, Some(1612345678000));
+    }
+
+    #[test]
+    fn parse_user_float_quota() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"id":"test","quota":{"free":123456789000.0,"used":2048,
+            "total":123456791048.0,"relative":0,"quota":-3}}}}"#;
+        let quota = parse::<User>(body).unwrap().quota.unwrap();
+        assert_eq!(quota.free, Some(123456789000));
+        assert_eq!(quota.used, Some(2048));
+    }
+
+    #[test]
+    fn parse_user_list() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"users":["alice","bob"]}}}"#;
+        let list: UserList = parse(body).unwrap();
+        assert_eq!(list.users, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn parse_group_list() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"groups":["admin","staff"]}}}"#;
+        let list: GroupList = parse(body).unwrap();
+        assert_eq!(list.groups, vec!["admin", "staff"]);
+
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":["alice"]}}"#;
+        let subadmins: Vec<String> = parse(body).unwrap();
+        assert_eq!(subadmins, vec!["alice"]);
+    }
+
+    #[test]
+    fn parse_app_list() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"apps":["activity","files_sharing"]}}}"#;
+        let list: AppList = parse(body).unwrap();
+        assert_eq!(list.apps, vec!["activity", "files_sharing"]);
+    }
+
+    #[test]
+    fn parse_app_password() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":{"apppassword":"KXFJb-Pj8Ro-Rfkr4-q47CW-nwdWS"}}}"#;
+        let password: AppPassword = parse(body).unwrap();
+        assert_eq!(password.app_password, "KXFJb-Pj8Ro-Rfkr4-q47CW-nwdWS");
+    }
+
+    #[test]
+    fn parse_user_status() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":{"userId":"test","message":"In incident","messageId":null,
+            "messageIsPredefined":false,"icon":"🚨","clearAt":1612350000,
+            "status":"dnd","statusIsUserDefined":true}}}"#;
+        let status: UserStatus = parse(body).unwrap();
+        assert_eq!(status.status, "dnd");
+        assert_eq!(status.message.as_deref(), Some("In incident"));
+        assert_eq!(status.clear_at, Some(1612350000));
+    }
+
+    #[test]
+    fn parse_failure_status() {
+        let body = r#"{"ocs":{"meta":{"status":"failure","statuscode":997,"message":"Unauthorised"},"data":[]}}"#;
+        parse::<User>(body).expect_err("Status 997 should fail");
+    }
+
+    #[test]
+    fn parse_link_share() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":{"id":"42","share_type":3,"uid_owner":"test","permissions":1,
+            "stime":1612345678,"expiration":"2021-03-01 00:00:00","token":"AbCdEf",
+            "path":"/Documents/report.pdf","item_type":"file","share_with":null,
+            "url":"https://cloud.example.com/s/AbCdEf","hide_download":1}}}"#;
+        let share: Share = parse(body).unwrap();
+        assert_eq!(share.id, "42");
+        assert_eq!(share.share_type, ShareType::Link as u8);
+        assert_eq!(
+            share.url.as_deref(),
+            Some("https://cloud.example.com/s/AbCdEf")
+        );
+        assert!(share.hide_download);
+        assert_eq!(share.share_with, None);
+    }
+
+    #[test]
+    fn parse_share_list() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":[{"id":43,"share_type":0,"uid_owner":"test","permissions":19,
+            "stime":1612345678,"path":"/Project","item_type":"folder",
+            "share_with":"bob","share_with_displayname":"Bob"}]}}"#;
+        let shares: Vec<Share> = parse(body).unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].id, "43");
+        assert_eq!(ShareType::name(shares[0].share_type), "user");
+        assert_eq!(
+            Permissions(shares[0].permissions).to_string(),
+            "read,update,reshare"
+        );
+        assert!(!shares[0].hide_download);
+    }
+
+    #[test]
+    fn parse_remote_shares() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":[{"id":"7","remote":"https://other.cloud","remote_id":"12",
+            "share_token":"token","name":"/Data","owner":"alice","user":"test",
+            "mountpoint":"{{TemporaryMountPointName#/Data}}","accepted":0}]}}"#;
+        let shares: Vec<RemoteShare> = parse(body).unwrap();
+        assert_eq!(shares[0].id, "7");
+        assert_eq!(shares[0].remote, "https://other.cloud");
+        assert!(!shares[0].accepted);
+    }
+
+    #[test]
+    fn parse_notifications() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":[{"notification_id":61,"app":"files_sharing","user":"test",
+            "datetime":"2021-02-03T10:00:00+00:00","object_type":"remote_share",
+            "object_id":"13","subject":"alice shared Data with you","message":"",
+            "link":"https://cloud.example.com/apps/files","actions":[]}]}}"#;
+        let notifications: Vec<Notification> = parse(body).unwrap();
+        assert_eq!(notifications[0].notification_id, 61);
+        assert_eq!(notifications[0].subject, "alice shared Data with you");
+    }
+
+    #[test]
+    fn parse_activities() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":200,"message":"OK"},
+            "data":[{"activity_id":120,"app":"files","type":"file_changed",
+            "user":"alice","affecteduser":"test","subject":"alice changed report.pdf",
+            "message":"","object_type":"files","object_id":42,
+            "object_name":"/Project/report.pdf","objects":{"42":"/Project/report.pdf"},
+            "link":"https://cloud.example.com/apps/files","icon":"",
+            "datetime":"2021-02-03T10:00:00+00:00"}]}}"#;
+        let activities: Vec<Activity> = parse(body).unwrap();
+        assert_eq!(activities[0].activity_id, 120);
+        assert_eq!(activities[0].activity_type, "file_changed");
+        assert_eq!(activities[0].user.as_deref(), Some("alice"));
+    }
+
+    #[test]
+    fn parse_capabilities() {
+        let body = r#"{"ocs":{"meta":{"status":"ok","statuscode":100,"message":"OK"},
+            "data":{"version":{"major":21,"minor":0,"micro":1,"string":"21.0.1",
+            "edition":"","extendedSupport":false},
+            "capabilities":{"core":{"pollinterval":60,"webdav-root":"remote.php/webdav"},
+            "dav":{"chunking":"1.0"},
+            "files":{"bigfilechunking":true,"undelete":true,"versioning":true},
+            "files_sharing":{"api_enabled":false}}}}}"#;
+        let capabilities: Capabilities = parse(body).unwrap();
+        assert_eq!(capabilities.version.string, "21.0.1");
+        assert!(!capabilities.supports(Feature::ChunkingV2));
+        assert!(capabilities.supports(Feature::Trash));
+        assert!(capabilities.supports(Feature::Search));
+        capabilities.require(Feature::Versions).unwrap();
+        capabilities
+            .require(Feature::Sharing)
+            .expect_err("Sharing is disabled");
+    }
+
+    #[test]
+    fn chunking_version() {
+        let capabilities = |chunking: &str| Capabilities {
+            version: Version {
+                major: 21,
+                minor: 0,
+                micro: 1,
+                string: String::from("21.0.1"),
+                edition: String::new(),
+            },
+            capabilities: serde_json::json!({ "dav": { "chunking": chunking } }),
+        };
+        assert!(!capabilities("1.0").supports(Feature::ChunkingV2));
+        assert!(capabilities("2.0").supports(Feature::ChunkingV2));
+        assert!(!capabilities("").supports(Feature::ChunkingV2));
+    }
+
+    #[test]
+    fn parse_server_status() {
+        let body = r#"{"installed":true,"maintenance":false,"needsDbUpgrade":false,
+            "version":"21.0.1.1","versionstring":"21.0.1","edition":"",
+            "productname":"Nextcloud","extendedSupport":false}"#;
+        let status: ServerStatus = serde_json::from_str(body).unwrap();
+        assert!(status.installed);
+        assert!(!status.maintenance);
+        assert_eq!(status.version_string, "21.0.1");
+        assert_eq!(status.product_name, "Nextcloud");
+    }
+
+    #[test]
+    fn permissions() {
+        assert_eq!("read".parse::<Permissions>().unwrap(), Permissions(1));
+        assert_eq!(
+            "read, update,reshare".parse::<Permissions>().unwrap(),
+            Permissions(19)
+        );
+        assert_eq!("all".parse::<Permissions>().unwrap(), Permissions(31));
+        assert_eq!("share".parse::<Permissions>().unwrap(), Permissions(16));
+        "write".parse::<Permissions>().expect_err("Unknown permission");
+        assert_eq!(Permissions(5).to_string(), "read,create");
+    }
+}
@@ -0,0 +1,161 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Format command results are printed in, set by the global --output option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "Unknown output format '{}', expected text, json or csv",
+                s
+            )),
+        }
+    }
+}
+
+static FORMAT: Mutex<Format> = Mutex::new(Format::Text);
+
+/// Sets the format of every following output
+pub fn init(format: Format) {
+    *FORMAT.lock().unwrap() = format;
+}
+
+pub fn format() -> Format {
+    *FORMAT.lock().unwrap()
+}
+
+pub fn is_text() -> bool {
+    format() == Format::Text
+}
+
+/// Prints the result of a command, `text` prints it for people
+pub fn print<T: Serialize + ?Sized>(
+    data: &T,
+    text: impl FnOnce(),
+) -> Result<()> {
+    match format() {
+        Format::Text => text(),
+        Format::Json => println!("{}", serde_json::to_string_pretty(data)?),
+        Format::Csv => print!("{}", to_csv(&serde_json::to_value(data)?)),
+    }
+    Ok(())
+}
+
+/// Prints an error as json or csv, text errors are left to the caller
+///
+/// The error goes to stdout like any result, so scripts tell a failure apart by the exit code,
+/// a command that printed its results before failing prints the error after them
+pub fn error(error: &anyhow::Error) {
+    let message = format!("{:#}", error);
+    match format() {
+        Format::Text => {}
+        Format::Json => println!("{:#}", json!({ "error": message })),
+        Format::Csv => print!("{}", to_csv(&json!({ "error": message }))),
+    }
+}
+
+/// Converts a value to csv, an array is a row per element and an object a single row
+/// with a header of the object keys, nested values are written as json
+fn to_csv(value: &Value) -> String {
+    let rows = match value {
+        Value::Array(rows) => rows.iter().collect(),
+        Value::Null => vec![],
+        row => vec![row],
+    };
+
+    let mut header: Vec<&String> = vec![];
+    for row in &rows {
+        if let Value::Object(map) = row {
+            for key in map.keys() {
+                if !header.contains(&key) {
+                    header.push(key);
+                }
+            }
+        }
+    }
+
+    let mut csv = String::new();
+    if !header.is_empty() {
+        let names: Vec<String> = header.iter().map(|k| csv_field(k)).collect();
+        csv.push_str(&names.join(","));
+        csv.push('\n');
+    }
+    for row in rows {
+        let fields: Vec<String> = match row {
+            Value::Object(map) => header
+                .iter()
+                .map(|key| csv_value(map.get(*key).unwrap_or(&Value::Null)))
+                .collect(),
+            value => vec![csv_value(value)],
+        };
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => csv_field(s),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+        nested => csv_field(&nested.to_string()),
+    }
+}
+
+/// Quotes a field if it contains a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_parse() {
+        assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+        assert_eq!("CSV".parse::<Format>().unwrap(), Format::Csv);
+        "xml".parse::<Format>().expect_err("Unknown format");
+    }
+
+    #[test]
+    fn csv_rows() {
+        let value = json!([
+            {"id": 1, "path": "/Documents", "tags": ["a", "b"], "owner": null},
+            {"id": 2, "path": "/a, \"b\"", "extra": true}
+        ]);
+        assert_eq!(
+            to_csv(&value),
+            "id,path,tags,owner,extra\n\
+             1,/Documents,\"[\"\"a\"\",\"\"b\"\"]\",,\n\
+             2,\"/a, \"\"b\"\"\",,,true\n"
+        );
+    }
+
+    #[test]
+    fn csv_single() {
+        assert_eq!(to_csv(&json!({"status": "dnd"})), "status\ndnd\n");
+        assert_eq!(to_csv(&json!(["alice", "bob"])), "alice\nbob\n");
+    }
+}
@@ -1,9 +1,12 @@
 use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
 
+use age::secrecy::SecretString;
 use anyhow::anyhow;
+use chrono::{Local, TimeZone};
 use path_dedot::ParseDot;
-use rustyline::{error::ReadlineError, Editor};
+use rustyline::{error::ReadlineError, Config, Editor, OutputStreamType};
+use url::Url;
 
 /// Formats the source to be url safe for the pull
 pub fn format_source_pull(source: &Path) -> anyhow::Result<PathBuf> {
@@ -63,7 +66,7 @@ fn get_source_file_name(source: &Path) -> anyhow::Result<OsString> {
 
 /// Checks if a generic path is pointing to a file as opposed to a directory
 /// Directory is defined atm as ending with '.','..','/','*', though star is just multiple files, cant support it atm
-fn path_is_file(path: &Path) -> bool {
+pub fn path_is_file(path: &Path) -> bool {
     let path_str = path.to_string_lossy();
     !(path_str.ends_with('.')
         || path_str.ends_with('/')
@@ -115,7 +118,10 @@ fn path_with_file_name(path: &Path, file_name: &Path) -> PathBuf {
 }
 
 pub fn get_confirmation(warning: &str) -> anyhow::Result<bool> {
-    let mut rl = Editor::<()>::new();
+    // the prompt goes to stderr to keep json and csv output on stdout parsable
+    let config =
+        Config::builder().output_stream(OutputStreamType::Stderr).build();
+    let mut rl = Editor::<()>::with_config(config);
     let prompt = format!("{}\n>> ", warning);
     let readline = rl.readline(&prompt);
 
@@ -127,18 +133,149 @@ pub fn get_confirmation(warning: &str) -> anyhow::Result<bool> {
             }
         }
         Err(ReadlineError::Interrupted) => {
-            println!("CTRL-C");
+            eprintln!("CTRL-C");
         }
         Err(ReadlineError::Eof) => {
-            println!("CTRL-D");
+            eprintln!("CTRL-D");
         }
         Err(err) => {
-            println!("Error: {:?}", err);
+            eprintln!("Error: {:?}", err);
         }
     }
     Ok(false)
 }
 
+/// Reads a passphrase from the `NXCLOUD_PASSPHRASE` environment variable or prompts for one without echo
+pub fn get_passphrase(prompt: &str) -> anyhow::Result<SecretString> {
+    if let Ok(passphrase) = std::env::var("NXCLOUD_PASSPHRASE") {
+        return Ok(SecretString::from(passphrase));
+    }
+    Ok(SecretString::from(rpassword::prompt_password(prompt)?))
+}
+
+/// Prompts for a new passphrase twice and makes sure they match
+pub fn get_new_passphrase() -> anyhow::Result<SecretString> {
+    let passphrase = match std::env::var("NXCLOUD_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let passphrase = rpassword::prompt_password("New passphrase: ")?;
+            if passphrase != rpassword::prompt_password("Confirm passphrase: ")?
+            {
+                return Err(anyhow!("Passphrases do not match"));
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        return Err(anyhow!("Passphrase cannot be empty"));
+    }
+    Ok(SecretString::from(passphrase))
+}
+
+/// Splits a public share link into the server url and the share token
+/// Ex: https://cloud.example.com/index.php/s/TOKEN returns https://cloud.example.com/ and TOKEN
+pub fn parse_public_link(link: &Url) -> anyhow::Result<(Url, String)> {
+    let segments: Vec<&str> = link
+        .path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let position = segments
+        .iter()
+        .rposition(|s| *s == "s")
+        .filter(|p| p + 1 < segments.len())
+        .ok_or_else(|| anyhow!("'{}' is not a public share link", link))?;
+
+    let mut base = &segments[..position];
+    if base.last() == Some(&"index.php") {
+        base = &base[..base.len() - 1];
+    }
+
+    let mut server = link.clone();
+    server.set_query(None);
+    server.set_fragment(None);
+    server.set_path(&format!("{}/", base.join("/")));
+    Ok((server, segments[position + 1].to_string()))
+}
+
+/// Formats a size in bytes as a human readable string using binary units
+/// Ex: 1536 returns 1.5 KiB
+pub fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Converts a glob pattern to a sql like pattern, * becomes % and ? becomes _
+/// If `contains` is set a pattern without wildcards matches anywhere, Ex: report returns %report%
+pub fn glob_to_like(glob: &str, contains: bool) -> String {
+    let like = glob.replace('*', "%").replace('?', "_");
+    if contains && !glob.contains(['*', '?']) {
+        format!("%{}%", like)
+    } else {
+        like
+    }
+}
+
+/// Parses a size with an optional binary unit suffix into bytes
+/// Ex: 10M returns 10485760
+pub fn parse_size(src: &str) -> Result<i64, String> {
+    let src = src.trim();
+    let split = src.find(|c: char| c.is_alphabetic()).unwrap_or(src.len());
+    let (number, unit) = src.split_at(split);
+    let number: f64 =
+        number.trim().parse().map_err(|_| format!("Invalid size '{}'", src))?;
+    let unit = unit.trim().to_uppercase();
+    let multiplier: i64 = match unit.trim_end_matches('B').trim_end_matches('I')
+    {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return Err(format!("Invalid size unit '{}'", unit)),
+    };
+    Ok((number * multiplier as f64) as i64)
+}
+
+/// Parses a duration with a unit suffix into seconds
+/// Ex: 30m returns 1800, units are s, m, h, d and w
+pub fn parse_duration(src: &str) -> Result<i64, String> {
+    let src = src.trim();
+    let split = src.find(|c: char| c.is_alphabetic()).unwrap_or(src.len());
+    let (number, unit) = src.split_at(split);
+    let number: i64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", src))?;
+    let multiplier = match unit.trim().to_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        unit => return Err(format!("Invalid duration unit '{}'", unit)),
+    };
+    Ok(number * multiplier)
+}
+
+/// Formats a unix timestamp in seconds as a local date and time
+pub fn format_timestamp(secs: i64) -> String {
+    match Local.timestamp_opt(secs, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => secs.to_string(),
+    }
+}
+
 pub fn join_dedot_path(
     start: PathBuf,
     end: PathBuf,
@@ -156,18 +293,12 @@ pub fn join_dedot_path(
 
     if end.starts_with("/") {
         match end.parse_dot() {
-            Ok(p) => {
-                Ok(PathBuf::from("/")
-                    .join(path_remove_prefix(&p.to_path_buf())))
-            }
+            Ok(p) => Ok(PathBuf::from("/").join(path_remove_prefix(&p))),
             Err(_) => Ok(PathBuf::from("/")),
         }
     } else {
         match start.join(end).parse_dot() {
-            Ok(p) => {
-                Ok(PathBuf::from("/")
-                    .join(path_remove_prefix(&p.to_path_buf())))
-            }
+            Ok(p) => Ok(PathBuf::from("/").join(path_remove_prefix(&p))),
             Err(_) => Ok(PathBuf::from("/")),
         }
     }
@@ -406,6 +537,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn public_link() {
+        let link = Url::parse("https://cloud.example.com/s/AbCdEf").unwrap();
+        let (server, token) = parse_public_link(&link).unwrap();
+        assert_eq!(server.as_str(), "https://cloud.example.com/");
+        assert_eq!(token, "AbCdEf");
+
+        let link =
+            Url::parse("https://example.com/nextcloud/index.php/s/AbCdEf/")
+                .unwrap();
+        let (server, token) = parse_public_link(&link).unwrap();
+        assert_eq!(server.as_str(), "https://example.com/nextcloud/");
+        assert_eq!(token, "AbCdEf");
+
+        let link = Url::parse("https://cloud.example.com/apps/files").unwrap();
+        parse_public_link(&link).expect_err("Not a share link");
+    }
+
+    #[test]
+    fn glob_like() {
+        assert_eq!(glob_to_like("report", true), "%report%");
+        assert_eq!(glob_to_like("*.pdf", true), "%.pdf");
+        assert_eq!(glob_to_like("file?.txt", true), "file_.txt");
+        assert_eq!(glob_to_like("image/*", false), "image/%");
+        assert_eq!(glob_to_like("image/png", false), "image/png");
+    }
+
+    #[test]
+    fn size_parse() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("1k").unwrap(), 1024);
+        assert_eq!(parse_size("1.5KiB").unwrap(), 1536);
+        assert_eq!(parse_size("10M").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("2 GB").unwrap(), 2 * 1024 * 1024 * 1024);
+        parse_size("ten").unwrap_err();
+        parse_size("10X").unwrap_err();
+    }
+
+    #[test]
+    fn new_passphrase_env_empty() {
+        std::env::set_var("NXCLOUD_PASSPHRASE", "");
+        let result = get_new_passphrase();
+        std::env::remove_var("NXCLOUD_PASSPHRASE");
+        result.expect_err("Empty passphrase should fail");
+    }
+
+    #[test]
+    fn duration_parse() {
+        assert_eq!(parse_duration("45").unwrap(), 45);
+        assert_eq!(parse_duration("30m").unwrap(), 1800);
+        assert_eq!(parse_duration("1h").unwrap(), 3600);
+        assert_eq!(parse_duration("2d").unwrap(), 2 * 24 * 3600);
+        parse_duration("1.5h").unwrap_err();
+        parse_duration("1y").unwrap_err();
+    }
+
+    #[test]
+    fn human_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(10 * 1024 * 1024 * 1024), "10.0 GiB");
+    }
+
     #[test]
     fn default_path_dedot_join() {
         let base = PathBuf::from("/");